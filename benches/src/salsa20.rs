@@ -0,0 +1,196 @@
+//! Salsa20 benchmark
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use benches::{criterion_group_bench, Benchmarker};
+
+use salsa20::{
+    cipher::{consts::U10, consts::U32, KeyIvInit, StreamCipher},
+    hsalsa, hsalsa_many, Salsa12, Salsa20, Salsa20Session, Salsa8, SalsaCore, XSalsa20,
+};
+
+const KB: usize = 1024;
+const MB: usize = 1024 * KB;
+
+/// Buffer sizes for the throughput benchmarks, from a single block up to bulk
+/// data which no longer fits into the caches.
+const SIZES: &[usize] = &[64, 256, KB, 4 * KB, 16 * KB, 64 * KB, MB, 16 * MB];
+
+fn bench_cipher<C: KeyIvInit + StreamCipher>(c: &mut Benchmarker, name: &str) {
+    let mut group = c.benchmark_group(name);
+
+    for size in SIZES {
+        let mut buf = vec![0u8; *size];
+
+        group.throughput(Throughput::Bytes(*size as u64));
+
+        group.bench_function(BenchmarkId::new("apply_keystream", size), |b| {
+            let key = Default::default();
+            let nonce = Default::default();
+            let mut cipher = C::new(&key, &nonce);
+            b.iter(|| cipher.apply_keystream(&mut buf));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_blocks(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("Salsa20-blocks");
+
+    for size in &[KB, 2 * KB, 4 * KB, 8 * KB, 16 * KB] {
+        let mut buf = vec![0u8; *size];
+
+        group.throughput(Throughput::Bytes(*size as u64));
+
+        group.bench_function(BenchmarkId::new("apply_keystream", size), |b| {
+            let key = Default::default();
+            let nonce = Default::default();
+            let mut cipher = Salsa20::new(&key, &nonce);
+            b.iter(|| {
+                for chunk in buf.chunks_mut(64) {
+                    cipher.apply_keystream(chunk);
+                }
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("apply_keystream_blocks", size), |b| {
+            let key = Default::default();
+            let nonce = Default::default();
+            let mut core = SalsaCore::<U10, U32>::new(&key, &nonce);
+            b.iter(|| core.apply_keystream_blocks(&mut buf).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+/// XSalsa20 shares the backend of Salsa20, so bulk throughput should match.
+fn bench_bulk(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("bulk");
+    let mut buf = vec![0u8; MB];
+    group.throughput(Throughput::Bytes(MB as u64));
+
+    group.bench_function("Salsa20", |b| {
+        let mut cipher = Salsa20::new(&Default::default(), &Default::default());
+        b.iter(|| cipher.apply_keystream(&mut buf));
+    });
+
+    group.bench_function("XSalsa20", |b| {
+        let mut cipher = XSalsa20::new(&Default::default(), &Default::default());
+        b.iter(|| cipher.apply_keystream(&mut buf));
+    });
+
+    group.finish();
+}
+
+fn bench_key_setup(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("key-setup");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("Salsa20", |b| {
+        let key = Default::default();
+        let nonce = Default::default();
+        b.iter(|| Salsa20::new(&key, &nonce));
+    });
+
+    group.bench_function("XSalsa20", |b| {
+        let key = Default::default();
+        let nonce = Default::default();
+        b.iter(|| XSalsa20::new(&key, &nonce));
+    });
+
+    group.finish();
+}
+
+/// Many small messages, each with a cipher of its own, as on a server
+/// encrypting packets: key setup dominates the cost here.
+fn bench_small_messages(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("small-messages");
+
+    for size in &[16, 64, 256] {
+        let mut msgs = vec![vec![0u8; *size]; 64];
+        group.throughput(Throughput::Elements(msgs.len() as u64));
+
+        group.bench_function(BenchmarkId::new("Salsa20", size), |b| {
+            let key = Default::default();
+            b.iter(|| {
+                for (i, msg) in msgs.iter_mut().enumerate() {
+                    let nonce = (i as u64).to_le_bytes().into();
+                    Salsa20::new(&key, &nonce).apply_keystream(msg);
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Many small messages encrypted with the same stream.
+fn bench_session(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("session");
+
+    for size in &[16, 64, 256] {
+        let mut msgs = vec![vec![0u8; *size]; 64];
+        group.throughput(Throughput::Bytes((msgs.len() * size) as u64));
+
+        group.bench_function(BenchmarkId::new("Salsa20", size), |b| {
+            let mut cipher = Salsa20::new(&Default::default(), &Default::default());
+            b.iter(|| {
+                for msg in msgs.iter_mut() {
+                    cipher.apply_keystream(msg);
+                }
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("Salsa20Session", size), |b| {
+            let mut session = Salsa20Session::new(&Default::default(), &Default::default());
+            b.iter(|| {
+                for msg in msgs.iter_mut() {
+                    session.encrypt(msg);
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_xsalsa_setup(c: &mut Benchmarker) {
+    let mut group = c.benchmark_group("XSalsa20-setup");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("hsalsa", |b| {
+        let key = Default::default();
+        let input = Default::default();
+        b.iter(|| hsalsa::<U10>(&key, &input));
+    });
+
+    group.throughput(Throughput::Elements(16));
+    group.bench_function("hsalsa_many/16", |b| {
+        let key = Default::default();
+        let inputs = [[0u8; 16]; 16];
+        let mut out = [[0u8; 32]; 16];
+        b.iter(|| hsalsa_many::<U10>(&key, &inputs, &mut out));
+    });
+
+    group.finish();
+}
+
+fn bench(c: &mut Benchmarker) {
+    bench_cipher::<Salsa8>(c, "Salsa8");
+    bench_cipher::<Salsa12>(c, "Salsa12");
+    bench_cipher::<Salsa20>(c, "Salsa20");
+    bench_cipher::<XSalsa20>(c, "XSalsa20");
+    bench_blocks(c);
+    bench_bulk(c);
+    bench_key_setup(c);
+    bench_small_messages(c);
+    bench_session(c);
+    bench_xsalsa_setup(c);
+}
+
+criterion_group_bench!(
+    benches,
+    bench
+);
+
+criterion_main!(benches);
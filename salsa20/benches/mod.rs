@@ -1,6 +1,7 @@
 #![feature(test)]
 extern crate test;
 
+#[cfg(feature = "reduced-round")]
 cipher::stream_cipher_bench!(
     salsa20::Salsa8;
     salsa8_bench1_16b 16;
@@ -9,6 +10,7 @@ cipher::stream_cipher_bench!(
     salsa8_bench4_16kib 16384;
 );
 
+#[cfg(feature = "reduced-round")]
 cipher::stream_cipher_bench!(
     salsa20::Salsa12;
     salsa12_bench1_16b 16;
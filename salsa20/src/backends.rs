@@ -1,3 +1,88 @@
+use cfg_if::cfg_if;
+
 pub(crate) mod soft;
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-pub(crate) mod sse2;
+
+cfg_if! {
+    if #[cfg(salsa20_force_soft)] {
+        // Only the software backend is used
+    } else if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+        pub(crate) mod sse2;
+        cfg_if! {
+            if #[cfg(salsa20_force_sse2)] {
+                // Only the SSE2 backend is used
+            } else if #[cfg(target_feature = "avx512f")] {
+                pub(crate) mod avx512;
+            } else {
+                pub(crate) mod avx2;
+            }
+        }
+    } else if #[cfg(all(target_arch = "aarch64", target_feature = "neon"))] {
+        pub(crate) mod neon;
+    } else if #[cfg(all(target_arch = "arm", target_feature = "neon", salsa20_arm_neon))] {
+        pub(crate) mod neon_arm;
+    } else if #[cfg(all(target_arch = "powerpc64", target_feature = "altivec", salsa20_altivec))] {
+        pub(crate) mod altivec;
+    } else if #[cfg(all(target_arch = "riscv64", target_feature = "v"))] {
+        pub(crate) mod rvv;
+    } else if #[cfg(all(target_arch = "loongarch64", target_feature = "lsx", salsa20_loongarch))] {
+        pub(crate) mod loongarch;
+    } else if #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))] {
+        pub(crate) mod wasm;
+    }
+}
+
+/// Whether the state is stored in the diagonal layout used by the x86 SIMD
+/// backends instead of the canonical Salsa20 order.
+///
+/// This is the single source of truth for the state layout: it holds exactly
+/// when the x86 backends are compiled in, and the x86 software fallback
+/// converts the state to canonical order before running.
+pub(crate) const DIAGONAL: bool = cfg!(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(salsa20_force_soft)
+));
+
+/// Index of the state word holding the high half of the block counter.
+///
+/// The low half is stored in word 8 in both layouts.
+pub(crate) const CTR_HI: usize = if DIAGONAL { 5 } else { 9 };
+
+/// Position of each canonical state word in the diagonal layout used by the
+/// x86 SIMD backends.
+///
+/// Every 128-bit row of the diagonal layout holds one diagonal of the 4x4
+/// state matrix, so a column round followed by a lane rotation of rows 1-3
+/// computes the row round. The AVX2 and AVX-512 backends keep one block per
+/// 128-bit lane and thus load each lane in exactly the same order.
+const DIAGONAL_LAYOUT: [usize; crate::STATE_WORDS] =
+    [0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11];
+
+/// Reorder canonical state words into the layout used by the backends.
+#[inline(always)]
+pub(crate) const fn to_layout(state: &[u32; crate::STATE_WORDS]) -> [u32; crate::STATE_WORDS] {
+    if !DIAGONAL {
+        return *state;
+    }
+    let mut res = [0u32; crate::STATE_WORDS];
+    // `while` loop so the function can be evaluated in const context
+    let mut i = 0;
+    while i < crate::STATE_WORDS {
+        res[i] = state[DIAGONAL_LAYOUT[i]];
+        i += 1;
+    }
+    res
+}
+
+/// Reorder state words from the layout used by the backends back into
+/// canonical order.
+#[inline(always)]
+pub(crate) fn from_layout(state: &[u32; crate::STATE_WORDS]) -> [u32; crate::STATE_WORDS] {
+    if !DIAGONAL {
+        return *state;
+    }
+    let mut res = [0u32; crate::STATE_WORDS];
+    for (s, &i) in state.iter().zip(DIAGONAL_LAYOUT.iter()) {
+        res[i] = *s;
+    }
+    res
+}
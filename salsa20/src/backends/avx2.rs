@@ -0,0 +1,233 @@
+//! AVX2 backend which processes four blocks in parallel, two per 256-bit
+//! register. Each 128-bit lane uses the same diagonal layout as the SSE2
+//! backend.
+
+use crate::{
+    backends::{sse2, CTR_HI},
+    Block, StreamCipherClosure, Unsigned, STATE_WORDS,
+};
+use cipher::{
+    array::ArraySize,
+    consts::{U4, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::marker::PhantomData;
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// Number of blocks processed in parallel.
+const PAR_BLOCKS: usize = 4;
+/// Number of `__m256i` sets needed to store parallel blocks.
+const N: usize = PAR_BLOCKS / 2;
+
+#[inline]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let state_ptr = state.as_ptr() as *const __m128i;
+    let mut backend = Backend::<R, K> {
+        v: [
+            _mm_loadu_si128(state_ptr.add(0)),
+            _mm_loadu_si128(state_ptr.add(1)),
+            _mm_loadu_si128(state_ptr.add(2)),
+            _mm_loadu_si128(state_ptr.add(3)),
+        ],
+        ctr: (state[8] as u64) | ((state[CTR_HI] as u64) << 32),
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[CTR_HI] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    v: [__m128i; 4],
+    /// 64-bit block counter.
+    ///
+    /// Kept outside of `v` so that the carry into the high word is handled
+    /// by plain integer arithmetic when a parallel group crosses 2^32.
+    ctr: u64,
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U4;
+}
+
+impl<R: Unsigned, K: ArraySize> Backend<R, K> {
+    /// Build the SSE2-layout state rows for the block at position `ctr`.
+    ///
+    /// In the diagonal layout the low counter word is lane 0 of row 2 and
+    /// the high counter word is lane 1 of row 1.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn block_rows(&self, ctr: u64) -> [__m128i; 4] {
+        [
+            self.v[0],
+            _mm_insert_epi32(self.v[1], (ctr >> 32) as i32, 1),
+            _mm_insert_epi32(self.v[2], ctr as i32, 0),
+            self.v[3],
+        ]
+    }
+
+    /// Interleave the rows of two blocks into a single 256-bit set, with
+    /// block `lo` in the low lane and block `hi` in the high lane.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn pair_rows(&self, lo: u64, hi: u64) -> [__m256i; 4] {
+        let a = self.block_rows(lo);
+        let b = self.block_rows(hi);
+        [
+            _mm256_set_m128i(b[0], a[0]),
+            _mm256_set_m128i(b[1], a[1]),
+            _mm256_set_m128i(b[2], a[2]),
+            _mm256_set_m128i(b[3], a[3]),
+        ]
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        unsafe {
+            let res = sse2::rounds::<R>(&self.block_rows(self.ctr));
+            self.ctr = self.ctr.wrapping_add(1);
+
+            let block_ptr = block.as_mut_ptr() as *mut __m128i;
+            for (i, v) in res.iter().enumerate() {
+                _mm_storeu_si128(block_ptr.add(i), *v);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        unsafe {
+            let mut vs = [[_mm256_setzero_si256(); 4]; N];
+            for (i, v) in vs.iter_mut().enumerate() {
+                let lo = self.ctr.wrapping_add(2 * i as u64);
+                *v = self.pair_rows(lo, lo.wrapping_add(1));
+            }
+            self.ctr = self.ctr.wrapping_add(PAR_BLOCKS as u64);
+
+            let res = rounds::<R>(&vs);
+
+            for (i, v) in res.iter().enumerate() {
+                let lo_ptr = blocks[2 * i].as_mut_ptr() as *mut __m128i;
+                let hi_ptr = blocks[2 * i + 1].as_mut_ptr() as *mut __m128i;
+                for (j, row) in v.iter().enumerate() {
+                    _mm_storeu_si128(lo_ptr.add(j), _mm256_castsi256_si128(*row));
+                    _mm_storeu_si128(hi_ptr.add(j), _mm256_extracti128_si256(*row, 1));
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn rounds<R: Unsigned>(v: &[[__m256i; 4]; N]) -> [[__m256i; 4]; N] {
+    let mut res = *v;
+
+    for _ in 0..R::USIZE {
+        for r in res.iter_mut() {
+            double_round(r);
+        }
+    }
+
+    for (r, v) in res.iter_mut().zip(v.iter()) {
+        for i in 0..4 {
+            r[i] = _mm256_add_epi32(r[i], v[i]);
+        }
+
+        transpose(r);
+        r[1] = _mm256_shuffle_epi32(r[1], 0b_10_01_00_11);
+        r[2] = _mm256_shuffle_epi32(r[2], 0b_01_00_11_10);
+        r[3] = _mm256_shuffle_epi32(r[3], 0b_00_11_10_01);
+        transpose(r);
+    }
+
+    res
+}
+
+/// The Salsa20 doubleround function for AVX2, see the SSE2 backend.
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn double_round([a, b, c, d]: &mut [__m256i; 4]) {
+    let mut t_sum: __m256i;
+    let mut t_rotl: __m256i;
+
+    // Operate on "columns"
+    t_sum = _mm256_add_epi32(*a, *d);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 7), _mm256_srli_epi32(t_sum, 25));
+    *b = _mm256_xor_si256(*b, t_rotl);
+
+    t_sum = _mm256_add_epi32(*b, *a);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 9), _mm256_srli_epi32(t_sum, 23));
+    *c = _mm256_xor_si256(*c, t_rotl);
+
+    t_sum = _mm256_add_epi32(*c, *b);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 13), _mm256_srli_epi32(t_sum, 19));
+    *d = _mm256_xor_si256(*d, t_rotl);
+
+    t_sum = _mm256_add_epi32(*d, *c);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 18), _mm256_srli_epi32(t_sum, 14));
+    *a = _mm256_xor_si256(*a, t_rotl);
+
+    // Rearrange data.
+    *b = _mm256_shuffle_epi32(*b, 0b_10_01_00_11);
+    *c = _mm256_shuffle_epi32(*c, 0b_01_00_11_10);
+    *d = _mm256_shuffle_epi32(*d, 0b_00_11_10_01);
+
+    // Operate on "rows".
+    t_sum = _mm256_add_epi32(*a, *b);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 7), _mm256_srli_epi32(t_sum, 25));
+    *d = _mm256_xor_si256(*d, t_rotl);
+
+    t_sum = _mm256_add_epi32(*d, *a);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 9), _mm256_srli_epi32(t_sum, 23));
+    *c = _mm256_xor_si256(*c, t_rotl);
+
+    t_sum = _mm256_add_epi32(*c, *d);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 13), _mm256_srli_epi32(t_sum, 19));
+    *b = _mm256_xor_si256(*b, t_rotl);
+
+    t_sum = _mm256_add_epi32(*b, *c);
+    t_rotl = _mm256_xor_si256(_mm256_slli_epi32(t_sum, 18), _mm256_srli_epi32(t_sum, 14));
+    *a = _mm256_xor_si256(*a, t_rotl);
+
+    // Rearrange data.
+    *b = _mm256_shuffle_epi32(*b, 0b_00_11_10_01);
+    *c = _mm256_shuffle_epi32(*c, 0b_01_00_11_10);
+    *d = _mm256_shuffle_epi32(*d, 0b_10_01_00_11);
+}
+
+/// Transpose the two integer 4 by 4 matrices held in the 128-bit lanes.
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn transpose([a, b, c, d]: &mut [__m256i; 4]) {
+    let t0 = _mm256_unpacklo_epi32(*a, *b);
+    let t1 = _mm256_unpacklo_epi32(*c, *d);
+    let t2 = _mm256_unpackhi_epi32(*a, *b);
+    let t3 = _mm256_unpackhi_epi32(*c, *d);
+
+    *a = _mm256_unpacklo_epi64(t0, t1);
+    *b = _mm256_unpackhi_epi64(t0, t1);
+    *c = _mm256_unpacklo_epi64(t2, t3);
+    *d = _mm256_unpackhi_epi64(t2, t3);
+}
@@ -0,0 +1,246 @@
+//! AVX-512 backend which processes eight blocks in parallel, four per 512-bit
+//! register. Each 128-bit lane uses the same diagonal layout as the SSE2
+//! backend.
+//!
+//! AVX-512 intrinsics are stable since Rust 1.89. This module is only compiled
+//! when the `avx512f` target feature is enabled, so the crate MSRV still
+//! applies to all other configurations.
+#![allow(clippy::incompatible_msrv)]
+
+use crate::{
+    backends::{sse2, CTR_HI},
+    Block, StreamCipherClosure, Unsigned, STATE_WORDS,
+};
+use cipher::{
+    array::ArraySize,
+    consts::{U64, U8},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::marker::PhantomData;
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// Number of blocks processed in parallel.
+const PAR_BLOCKS: usize = 8;
+/// Number of blocks stored in one `__m512i`.
+const LANES: usize = 4;
+/// Number of `__m512i` sets needed to store parallel blocks.
+const N: usize = PAR_BLOCKS / LANES;
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let state_ptr = state.as_ptr() as *const __m128i;
+    let mut backend = Backend::<R, K> {
+        v: [
+            _mm_loadu_si128(state_ptr.add(0)),
+            _mm_loadu_si128(state_ptr.add(1)),
+            _mm_loadu_si128(state_ptr.add(2)),
+            _mm_loadu_si128(state_ptr.add(3)),
+        ],
+        ctr: (state[8] as u64) | ((state[CTR_HI] as u64) << 32),
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[CTR_HI] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    v: [__m128i; 4],
+    /// 64-bit block counter, see the AVX2 backend.
+    ctr: u64,
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U8;
+}
+
+impl<R: Unsigned, K: ArraySize> Backend<R, K> {
+    /// Build the SSE2-layout state rows for the block at position `ctr`.
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn block_rows(&self, ctr: u64) -> [__m128i; 4] {
+        [
+            self.v[0],
+            _mm_insert_epi32(self.v[1], (ctr >> 32) as i32, 1),
+            _mm_insert_epi32(self.v[2], ctr as i32, 0),
+            self.v[3],
+        ]
+    }
+
+    /// Build a 512-bit set holding the blocks at positions `ctr..ctr + 4`,
+    /// one block per 128-bit lane.
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn quad_rows(&self, ctr: u64) -> [__m512i; 4] {
+        let b0 = self.block_rows(ctr);
+        let b1 = self.block_rows(ctr.wrapping_add(1));
+        let b2 = self.block_rows(ctr.wrapping_add(2));
+        let b3 = self.block_rows(ctr.wrapping_add(3));
+
+        let mut res = [_mm512_setzero_si512(); 4];
+        for (i, r) in res.iter_mut().enumerate() {
+            let lo = _mm256_set_m128i(b1[i], b0[i]);
+            let hi = _mm256_set_m128i(b3[i], b2[i]);
+            *r = _mm512_inserti64x4(_mm512_castsi256_si512(lo), hi, 1);
+        }
+        res
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        unsafe {
+            let res = sse2::rounds::<R>(&self.block_rows(self.ctr));
+            self.ctr = self.ctr.wrapping_add(1);
+
+            let block_ptr = block.as_mut_ptr() as *mut __m128i;
+            for (i, v) in res.iter().enumerate() {
+                _mm_storeu_si128(block_ptr.add(i), *v);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        unsafe {
+            let mut vs = [[_mm512_setzero_si512(); 4]; N];
+            for (i, v) in vs.iter_mut().enumerate() {
+                *v = self.quad_rows(self.ctr.wrapping_add((LANES * i) as u64));
+            }
+            self.ctr = self.ctr.wrapping_add(PAR_BLOCKS as u64);
+
+            let res = rounds::<R>(&vs);
+
+            for (i, v) in res.iter().enumerate() {
+                for (j, row) in v.iter().enumerate() {
+                    let lanes = [
+                        _mm512_extracti32x4_epi32(*row, 0),
+                        _mm512_extracti32x4_epi32(*row, 1),
+                        _mm512_extracti32x4_epi32(*row, 2),
+                        _mm512_extracti32x4_epi32(*row, 3),
+                    ];
+                    for (k, lane) in lanes.iter().enumerate() {
+                        let block_ptr = blocks[LANES * i + k].as_mut_ptr() as *mut __m128i;
+                        _mm_storeu_si128(block_ptr.add(j), *lane);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn rounds<R: Unsigned>(v: &[[__m512i; 4]; N]) -> [[__m512i; 4]; N] {
+    let mut res = *v;
+
+    for _ in 0..R::USIZE {
+        for r in res.iter_mut() {
+            double_round(r);
+        }
+    }
+
+    for (r, v) in res.iter_mut().zip(v.iter()) {
+        for i in 0..4 {
+            r[i] = _mm512_add_epi32(r[i], v[i]);
+        }
+
+        transpose(r);
+        r[1] = _mm512_shuffle_epi32(r[1], 0b_10_01_00_11);
+        r[2] = _mm512_shuffle_epi32(r[2], 0b_01_00_11_10);
+        r[3] = _mm512_shuffle_epi32(r[3], 0b_00_11_10_01);
+        transpose(r);
+    }
+
+    res
+}
+
+/// The Salsa20 doubleround function for AVX-512, see the SSE2 backend.
+///
+/// Unlike SSE2 and AVX2, AVX-512F has a 32-bit rotate instruction (`vprold`),
+/// which replaces the shift-shift-xor sequence of the other backends.
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn double_round([a, b, c, d]: &mut [__m512i; 4]) {
+    let mut t_sum: __m512i;
+    let mut t_rotl: __m512i;
+
+    // Operate on "columns"
+    t_sum = _mm512_add_epi32(*a, *d);
+    t_rotl = _mm512_rol_epi32::<7>(t_sum);
+    *b = _mm512_xor_si512(*b, t_rotl);
+
+    t_sum = _mm512_add_epi32(*b, *a);
+    t_rotl = _mm512_rol_epi32::<9>(t_sum);
+    *c = _mm512_xor_si512(*c, t_rotl);
+
+    t_sum = _mm512_add_epi32(*c, *b);
+    t_rotl = _mm512_rol_epi32::<13>(t_sum);
+    *d = _mm512_xor_si512(*d, t_rotl);
+
+    t_sum = _mm512_add_epi32(*d, *c);
+    t_rotl = _mm512_rol_epi32::<18>(t_sum);
+    *a = _mm512_xor_si512(*a, t_rotl);
+
+    // Rearrange data.
+    *b = _mm512_shuffle_epi32(*b, 0b_10_01_00_11);
+    *c = _mm512_shuffle_epi32(*c, 0b_01_00_11_10);
+    *d = _mm512_shuffle_epi32(*d, 0b_00_11_10_01);
+
+    // Operate on "rows".
+    t_sum = _mm512_add_epi32(*a, *b);
+    t_rotl = _mm512_rol_epi32::<7>(t_sum);
+    *d = _mm512_xor_si512(*d, t_rotl);
+
+    t_sum = _mm512_add_epi32(*d, *a);
+    t_rotl = _mm512_rol_epi32::<9>(t_sum);
+    *c = _mm512_xor_si512(*c, t_rotl);
+
+    t_sum = _mm512_add_epi32(*c, *d);
+    t_rotl = _mm512_rol_epi32::<13>(t_sum);
+    *b = _mm512_xor_si512(*b, t_rotl);
+
+    t_sum = _mm512_add_epi32(*b, *c);
+    t_rotl = _mm512_rol_epi32::<18>(t_sum);
+    *a = _mm512_xor_si512(*a, t_rotl);
+
+    // Rearrange data.
+    *b = _mm512_shuffle_epi32(*b, 0b_00_11_10_01);
+    *c = _mm512_shuffle_epi32(*c, 0b_01_00_11_10);
+    *d = _mm512_shuffle_epi32(*d, 0b_10_01_00_11);
+}
+
+/// Transpose the four integer 4 by 4 matrices held in the 128-bit lanes.
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn transpose([a, b, c, d]: &mut [__m512i; 4]) {
+    let t0 = _mm512_unpacklo_epi32(*a, *b);
+    let t1 = _mm512_unpacklo_epi32(*c, *d);
+    let t2 = _mm512_unpackhi_epi32(*a, *b);
+    let t3 = _mm512_unpackhi_epi32(*c, *d);
+
+    *a = _mm512_unpacklo_epi64(t0, t1);
+    *b = _mm512_unpackhi_epi64(t0, t1);
+    *c = _mm512_unpacklo_epi64(t2, t3);
+    *d = _mm512_unpackhi_epi64(t2, t3);
+}
@@ -0,0 +1,178 @@
+//! LSX-optimized implementation for LoongArch64 CPUs.
+//!
+//! Four blocks are processed in parallel. Each 128-bit vector holds the same
+//! state word of all four blocks, so the rounds need no lane shuffling.
+//!
+//! LoongArch is little-endian and vectors are only converted from and to
+//! `[u32; 4]` arrays, so the keystream is written out word by word as in the
+//! other word-sliced backends.
+
+use crate::{
+    backends::soft::Backend as SoftBackend, Block, StreamCipherClosure, Unsigned, STATE_WORDS,
+};
+use cipher::{
+    array::ArraySize,
+    consts::{U4, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::{arch::loongarch64::*, marker::PhantomData, mem::transmute};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+/// Number of blocks processed in parallel.
+const PAR_BLOCKS: usize = 4;
+
+// The LoongArch intrinsics are unstable, so this backend relies on LSX being
+// enabled at compile time instead of runtime detection.
+#[inline]
+pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let mut v = [splat(0); STATE_WORDS];
+    for (v, s) in v.iter_mut().zip(state.iter()) {
+        *v = splat(*s);
+    }
+    let mut backend = Backend::<R, K> {
+        v,
+        ctr: (state[8] as u64) | ((state[9] as u64) << 32),
+        state: *state,
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[9] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+
+    #[cfg(feature = "zeroize")]
+    backend.state.zeroize();
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    /// State words broadcast to all lanes. The counter words are overwritten
+    /// with per-block values on every call.
+    v: [v4i32; STATE_WORDS],
+    /// 64-bit block counter.
+    ctr: u64,
+    /// State words in canonical order for the single-block path.
+    state: [u32; STATE_WORDS],
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U4;
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    /// Single blocks are computed with the scalar rounds, which is cheaper
+    /// than computing all four lanes and discarding three of them.
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        self.state[8] = (self.ctr & 0xffff_ffff) as u32;
+        self.state[9] = ((self.ctr >> 32) & 0xffff_ffff) as u32;
+        SoftBackend::<R>::new(&mut self.state).gen_ks_block(block);
+        self.ctr = self.ctr.wrapping_add(1);
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        unsafe { self.write_par_ks_blocks(blocks) }
+    }
+
+    #[inline(always)]
+    fn gen_tail_blocks(&mut self, blocks: &mut [Block<Self>]) {
+        if let [block] = blocks {
+            return self.gen_ks_block(block);
+        }
+        let n = blocks.len();
+        let mut par = ParBlocks::<Self>::default();
+        self.gen_par_ks_blocks(&mut par);
+        blocks.copy_from_slice(&par[..n]);
+        #[cfg(feature = "zeroize")]
+        for block in par.iter_mut() {
+            block[..].zeroize();
+        }
+        self.ctr = self.ctr.wrapping_sub((PAR_BLOCKS - n) as u64);
+    }
+}
+
+/// Broadcast `x` to all lanes.
+#[inline(always)]
+unsafe fn splat(x: u32) -> v4i32 {
+    transmute::<[u32; PAR_BLOCKS], v4i32>([x; PAR_BLOCKS])
+}
+
+#[inline(always)]
+unsafe fn xor(a: v4i32, b: v4i32) -> v4i32 {
+    transmute::<v16u8, v4i32>(lsx_vxor_v(transmute(a), transmute(b)))
+}
+
+/// LSX only rotates right, so rotating left by `$r` rotates right by `32 - $r`.
+macro_rules! rotate_left {
+    ($v:expr, $r:literal) => {
+        lsx_vrotri_w::<{ 32 - $r }>($v)
+    };
+}
+
+impl<R: Unsigned, K: ArraySize> Backend<R, K> {
+    #[inline(always)]
+    unsafe fn write_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        let mut lo = [0u32; PAR_BLOCKS];
+        let mut hi = [0u32; PAR_BLOCKS];
+        for i in 0..PAR_BLOCKS {
+            let ctr = self.ctr.wrapping_add(i as u64);
+            lo[i] = (ctr & 0xffff_ffff) as u32;
+            hi[i] = ((ctr >> 32) & 0xffff_ffff) as u32;
+        }
+        self.ctr = self.ctr.wrapping_add(PAR_BLOCKS as u64);
+
+        let mut state = self.v;
+        state[8] = transmute::<[u32; PAR_BLOCKS], v4i32>(lo);
+        state[9] = transmute::<[u32; PAR_BLOCKS], v4i32>(hi);
+
+        let mut x = state;
+        for _ in 0..R::USIZE {
+            double_round(&mut x);
+        }
+
+        for (i, (x, s)) in x.iter().zip(state.iter()).enumerate() {
+            let words = transmute::<v4i32, [u32; PAR_BLOCKS]>(lsx_vadd_w(*x, *s));
+            for (block, word) in blocks.iter_mut().zip(words.iter()) {
+                block[4 * i..][..4].copy_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[inline(always)]
+unsafe fn quarter_round(a: usize, b: usize, c: usize, d: usize, x: &mut [v4i32; STATE_WORDS]) {
+    x[b] = xor(x[b], rotate_left!(lsx_vadd_w(x[a], x[d]), 7));
+    x[c] = xor(x[c], rotate_left!(lsx_vadd_w(x[b], x[a]), 9));
+    x[d] = xor(x[d], rotate_left!(lsx_vadd_w(x[c], x[b]), 13));
+    x[a] = xor(x[a], rotate_left!(lsx_vadd_w(x[d], x[c]), 18));
+}
+
+#[inline(always)]
+unsafe fn double_round(x: &mut [v4i32; STATE_WORDS]) {
+    // column rounds
+    quarter_round(0, 4, 8, 12, x);
+    quarter_round(5, 9, 13, 1, x);
+    quarter_round(10, 14, 2, 6, x);
+    quarter_round(15, 3, 7, 11, x);
+
+    // diagonal rounds
+    quarter_round(0, 1, 2, 3, x);
+    quarter_round(5, 6, 7, 4, x);
+    quarter_round(10, 11, 8, 9, x);
+    quarter_round(15, 12, 13, 14, x);
+}
@@ -0,0 +1,178 @@
+//! NEON-optimized implementation for aarch64 CPUs.
+//!
+//! Four blocks are processed in parallel. Each vector holds the same state
+//! word of all four blocks, so the rounds need no lane shuffling and the
+//! blocks are only transposed back when written out.
+
+use crate::{
+    backends::soft::Backend as SoftBackend, Block, StreamCipherClosure, Unsigned, STATE_WORDS,
+};
+use cipher::{
+    array::ArraySize,
+    consts::{U4, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::{arch::aarch64::*, marker::PhantomData};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+/// Number of blocks processed in parallel.
+const PAR_BLOCKS: usize = 4;
+
+#[inline]
+#[target_feature(enable = "neon")]
+pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let mut v = [vdupq_n_u32(0); STATE_WORDS];
+    for (v, s) in v.iter_mut().zip(state.iter()) {
+        *v = vdupq_n_u32(*s);
+    }
+    let mut backend = Backend::<R, K> {
+        v,
+        ctr: (state[8] as u64) | ((state[9] as u64) << 32),
+        state: *state,
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[9] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+
+    #[cfg(feature = "zeroize")]
+    backend.state.zeroize();
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    /// State words broadcast to all lanes. The counter words are overwritten
+    /// with per-block values on every call.
+    v: [uint32x4_t; STATE_WORDS],
+    /// 64-bit block counter.
+    ctr: u64,
+    /// State words in canonical order for the single-block path.
+    state: [u32; STATE_WORDS],
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U4;
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    /// Single blocks are computed with the scalar rounds, which is cheaper
+    /// than computing all four lanes and discarding three of them.
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        self.state[8] = (self.ctr & 0xffff_ffff) as u32;
+        self.state[9] = ((self.ctr >> 32) & 0xffff_ffff) as u32;
+        SoftBackend::<R>::new(&mut self.state).gen_ks_block(block);
+        self.ctr = self.ctr.wrapping_add(1);
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        unsafe { self.write_par_ks_blocks(blocks) }
+    }
+
+    #[inline(always)]
+    fn gen_tail_blocks(&mut self, blocks: &mut [Block<Self>]) {
+        if let [block] = blocks {
+            return self.gen_ks_block(block);
+        }
+        let n = blocks.len();
+        let mut par = ParBlocks::<Self>::default();
+        self.gen_par_ks_blocks(&mut par);
+        blocks.copy_from_slice(&par[..n]);
+        #[cfg(feature = "zeroize")]
+        for block in par.iter_mut() {
+            block[..].zeroize();
+        }
+        self.ctr = self.ctr.wrapping_sub((PAR_BLOCKS - n) as u64);
+    }
+}
+
+macro_rules! rotate_left {
+    ($v:expr, $r:literal) => {{
+        let v = $v;
+        vsriq_n_u32(vshlq_n_u32(v, $r), v, 32 - $r)
+    }};
+}
+
+impl<R: Unsigned, K: ArraySize> Backend<R, K> {
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn write_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        let mut lo = [0u32; PAR_BLOCKS];
+        let mut hi = [0u32; PAR_BLOCKS];
+        for i in 0..PAR_BLOCKS {
+            let ctr = self.ctr.wrapping_add(i as u64);
+            lo[i] = (ctr & 0xffff_ffff) as u32;
+            hi[i] = ((ctr >> 32) & 0xffff_ffff) as u32;
+        }
+        self.ctr = self.ctr.wrapping_add(PAR_BLOCKS as u64);
+
+        let mut state = self.v;
+        state[8] = vld1q_u32(lo.as_ptr());
+        state[9] = vld1q_u32(hi.as_ptr());
+
+        let mut x = state;
+        for _ in 0..R::USIZE {
+            double_round(&mut x);
+        }
+        for (x, s) in x.iter_mut().zip(state.iter()) {
+            *x = vaddq_u32(*x, *s);
+        }
+
+        // Transpose each group of four words from word-major to block-major order
+        for (i, w) in x.chunks_exact(4).enumerate() {
+            let t0 = vtrn1q_u32(w[0], w[1]);
+            let t1 = vtrn2q_u32(w[0], w[1]);
+            let t2 = vtrn1q_u32(w[2], w[3]);
+            let t3 = vtrn2q_u32(w[2], w[3]);
+
+            let rows = [
+                vtrn1q_u64(vreinterpretq_u64_u32(t0), vreinterpretq_u64_u32(t2)),
+                vtrn1q_u64(vreinterpretq_u64_u32(t1), vreinterpretq_u64_u32(t3)),
+                vtrn2q_u64(vreinterpretq_u64_u32(t0), vreinterpretq_u64_u32(t2)),
+                vtrn2q_u64(vreinterpretq_u64_u32(t1), vreinterpretq_u64_u32(t3)),
+            ];
+            for (block, row) in blocks.iter_mut().zip(rows.iter()) {
+                vst1q_u8(block.as_mut_ptr().add(16 * i), vreinterpretq_u8_u64(*row));
+            }
+        }
+    }
+}
+
+#[inline(always)]
+unsafe fn quarter_round(a: usize, b: usize, c: usize, d: usize, x: &mut [uint32x4_t; STATE_WORDS]) {
+    x[b] = veorq_u32(x[b], rotate_left!(vaddq_u32(x[a], x[d]), 7));
+    x[c] = veorq_u32(x[c], rotate_left!(vaddq_u32(x[b], x[a]), 9));
+    x[d] = veorq_u32(x[d], rotate_left!(vaddq_u32(x[c], x[b]), 13));
+    x[a] = veorq_u32(x[a], rotate_left!(vaddq_u32(x[d], x[c]), 18));
+}
+
+#[inline(always)]
+unsafe fn double_round(x: &mut [uint32x4_t; STATE_WORDS]) {
+    // column rounds
+    quarter_round(0, 4, 8, 12, x);
+    quarter_round(5, 9, 13, 1, x);
+    quarter_round(10, 14, 2, 6, x);
+    quarter_round(15, 3, 7, 11, x);
+
+    // diagonal rounds
+    quarter_round(0, 1, 2, 3, x);
+    quarter_round(5, 6, 7, 4, x);
+    quarter_round(10, 11, 8, 9, x);
+    quarter_round(15, 12, 13, 14, x);
+}
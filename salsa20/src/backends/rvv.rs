@@ -0,0 +1,258 @@
+//! RISC-V vector extension (RVV) implementation for RISC-V 64 CPUs.
+//!
+//! Each vector register holds the same state word of several blocks, so the
+//! rounds need no lane shuffling. The vector length is only known at runtime:
+//! every batch is split into strips of as many blocks as `vsetvli` grants,
+//! i.e. `VLEN / 32` blocks per strip.
+//!
+//! The RVV intrinsics are not available in Rust yet, so the rounds are
+//! written in inline assembly. Rust marks `vl` and `vtype` as clobbered by
+//! every `asm!` block, and the vector registers used are declared as clobbers.
+
+use crate::{Block, StreamCipherClosure, Unsigned, STATE_WORDS};
+use cipher::{
+    array::ArraySize,
+    consts::{U16, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::{arch::asm, marker::PhantomData};
+
+/// Maximum number of blocks processed per batch. This is a single strip with
+/// a `VLEN` of 512 bits; smaller vector lengths need several strips.
+const PAR_BLOCKS: usize = 16;
+
+/// Distance in bytes between two consecutive words of the word-major buffers.
+const ROW_BYTES: usize = PAR_BLOCKS * 4;
+
+#[inline]
+pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let mut backend = Backend::<R, K> {
+        state: *state,
+        ctr: (state[8] as u64) | ((state[9] as u64) << 32),
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[9] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    /// State words. The counter words are overwritten with per-block values
+    /// on every call.
+    state: [u32; STATE_WORDS],
+    /// 64-bit block counter.
+    ctr: u64,
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U16;
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        unsafe { self.write_ks_blocks(core::slice::from_mut(block)) }
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        unsafe { self.write_ks_blocks(blocks) }
+    }
+
+    #[inline(always)]
+    fn gen_tail_blocks(&mut self, blocks: &mut [Block<Self>]) {
+        // The strips adapt to any number of blocks, so nothing is discarded
+        unsafe { self.write_ks_blocks(blocks) }
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> Backend<R, K> {
+    /// Write the keystream of up to `PAR_BLOCKS` blocks.
+    #[inline(always)]
+    unsafe fn write_ks_blocks(&mut self, blocks: &mut [Block<Self>]) {
+        let n = blocks.len();
+        debug_assert!(n <= PAR_BLOCKS);
+
+        let mut lo = [0u32; PAR_BLOCKS];
+        let mut hi = [0u32; PAR_BLOCKS];
+        for i in 0..n {
+            let ctr = self.ctr.wrapping_add(i as u64);
+            lo[i] = (ctr & 0xffff_ffff) as u32;
+            hi[i] = ((ctr >> 32) & 0xffff_ffff) as u32;
+        }
+        self.ctr = self.ctr.wrapping_add(n as u64);
+
+        // Word-major buffers: `input[i][j]` is word `i` of block `j`
+        let mut input = [[0u32; PAR_BLOCKS]; STATE_WORDS];
+        for (row, s) in input.iter_mut().zip(self.state.iter()) {
+            *row = [*s; PAR_BLOCKS];
+        }
+        input[8] = lo;
+        input[9] = hi;
+
+        let mut output = [[0u32; PAR_BLOCKS]; STATE_WORDS];
+        let mut done = 0;
+        while done < n {
+            done += strip::<R>(
+                input.as_ptr().cast::<u32>().add(done),
+                output.as_mut_ptr().cast::<u32>().add(done),
+                n - done,
+            );
+        }
+
+        for (i, row) in output.iter().enumerate() {
+            for (block, word) in blocks.iter_mut().zip(row.iter()) {
+                block[4 * i..][..4].copy_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Load state word `$i` of the strip into `v$i`.
+macro_rules! load {
+    ($i:literal) => {
+        concat!("vle32.v v", $i, ", ({p})\n", "addi {p}, {p}, {row}\n")
+    };
+}
+
+/// Add state word `$i` of the strip to `v$i` and store the result.
+macro_rules! add_store {
+    ($i:literal) => {
+        concat!(
+            "vle32.v v16, ({p})\n",
+            "addi {p}, {p}, {row}\n",
+            "vadd.vv v",
+            $i,
+            ", v",
+            $i,
+            ", v16\n",
+            "vse32.v v",
+            $i,
+            ", ({output})\n",
+            "addi {output}, {output}, {row}\n",
+        )
+    };
+}
+
+/// `v$x ^= (v$y + v$z) <<< $r`, where `$l` is `32 - $r`.
+macro_rules! step {
+    ($x:literal, $y:literal, $z:literal, $r:literal, $l:literal) => {
+        concat!(
+            "vadd.vv v16, v",
+            $y,
+            ", v",
+            $z,
+            "\n",
+            "vsll.vi v17, v16, ",
+            $r,
+            "\n",
+            "vsrl.vi v16, v16, ",
+            $l,
+            "\n",
+            "vor.vv v16, v16, v17\n",
+            "vxor.vv v",
+            $x,
+            ", v",
+            $x,
+            ", v16\n",
+        )
+    };
+}
+
+macro_rules! quarter_round {
+    ($a:literal, $b:literal, $c:literal, $d:literal) => {
+        concat!(
+            step!($b, $a, $d, 7, 25),
+            step!($c, $b, $a, 9, 23),
+            step!($d, $c, $b, 13, 19),
+            step!($a, $d, $c, 18, 14),
+        )
+    };
+}
+
+/// Generate the keystream of up to `avl` blocks from the word-major buffers
+/// at `input` and `output`, returning the number of blocks processed.
+#[inline(always)]
+unsafe fn strip<R: Unsigned>(input: *const u32, output: *mut u32, avl: usize) -> usize {
+    let vl: usize;
+    asm!(
+        "vsetvli {vl}, {avl}, e32, m1, ta, ma",
+        "mv {p}, {input}",
+        load!(0),
+        load!(1),
+        load!(2),
+        load!(3),
+        load!(4),
+        load!(5),
+        load!(6),
+        load!(7),
+        load!(8),
+        load!(9),
+        load!(10),
+        load!(11),
+        load!(12),
+        load!(13),
+        load!(14),
+        load!(15),
+        "beqz {rounds}, 3f",
+        "2:",
+        // column rounds
+        quarter_round!(0, 4, 8, 12),
+        quarter_round!(5, 9, 13, 1),
+        quarter_round!(10, 14, 2, 6),
+        quarter_round!(15, 3, 7, 11),
+        // diagonal rounds
+        quarter_round!(0, 1, 2, 3),
+        quarter_round!(5, 6, 7, 4),
+        quarter_round!(10, 11, 8, 9),
+        quarter_round!(15, 12, 13, 14),
+        "addi {rounds}, {rounds}, -1",
+        "bnez {rounds}, 2b",
+        "3:",
+        "mv {p}, {input}",
+        add_store!(0),
+        add_store!(1),
+        add_store!(2),
+        add_store!(3),
+        add_store!(4),
+        add_store!(5),
+        add_store!(6),
+        add_store!(7),
+        add_store!(8),
+        add_store!(9),
+        add_store!(10),
+        add_store!(11),
+        add_store!(12),
+        add_store!(13),
+        add_store!(14),
+        add_store!(15),
+        vl = out(reg) vl,
+        avl = in(reg) avl,
+        input = in(reg) input,
+        output = inout(reg) output => _,
+        rounds = inout(reg) R::USIZE => _,
+        p = out(reg) _,
+        row = const ROW_BYTES,
+        out("v0") _, out("v1") _, out("v2") _, out("v3") _,
+        out("v4") _, out("v5") _, out("v6") _, out("v7") _,
+        out("v8") _, out("v9") _, out("v10") _, out("v11") _,
+        out("v12") _, out("v13") _, out("v14") _, out("v15") _,
+        out("v16") _, out("v17") _,
+        options(nostack),
+    );
+    vl
+}
@@ -1,67 +1,244 @@
 //! Portable implementation which does not rely on architecture-specific
 //! intrinsics.
+//!
+//! The rounds consist of wrapping additions, XORs and rotations by constant
+//! amounts only, with no branches or memory accesses depending on the state.
+//! Loop counts depend only on the number of rounds and blocks.
 
-use crate::{Block, SalsaCore, Unsigned, STATE_WORDS};
+use crate::{Block, Unsigned, STATE_WORDS};
 use cipher::{
-    array::ArraySize,
-    consts::{U1, U64},
-    BlockSizeUser, ParBlocksSizeUser, StreamCipherBackend, StreamCipherSeekCore,
+    consts::{U4, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
 };
+use core::marker::PhantomData;
 
-pub(crate) struct Backend<'a, R: Unsigned, K: ArraySize>(pub(crate) &'a mut SalsaCore<R, K>);
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
 
-impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<'_, R, K> {
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(any(salsa20_force_soft, salsa20_force_sse2, target_feature = "avx512f"))
+))]
+use crate::{
+    backends::{from_layout, CTR_HI},
+    StreamCipherClosure,
+};
+
+/// Run the software backend on a state stored in the x86 diagonal layout, for
+/// CPUs on which the runtime dispatch found no SIMD support.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(any(salsa20_force_soft, salsa20_force_sse2, target_feature = "avx512f"))
+))]
+#[inline]
+pub(crate) fn inner<R, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let mut canonical = from_layout(state);
+    f.call(&mut Backend::<R>::new(&mut canonical));
+    state[8] = canonical[8];
+    state[CTR_HI] = canonical[9];
+
+    #[cfg(feature = "zeroize")]
+    canonical.zeroize();
+}
+
+/// Number of blocks computed together by the unrolled inner loop.
+const PAR_BLOCKS: usize = 4;
+
+/// Software backend operating on the state words in canonical order.
+// Unused on targets where a SIMD backend handles every variant
+#[allow(dead_code)]
+pub(crate) struct Backend<'a, R: Unsigned> {
+    state: &'a mut [u32; STATE_WORDS],
+    _pd: PhantomData<R>,
+}
+
+#[allow(dead_code)]
+impl<'a, R: Unsigned> Backend<'a, R> {
+    #[inline(always)]
+    pub(crate) fn new(state: &'a mut [u32; STATE_WORDS]) -> Self {
+        Self {
+            state,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<R: Unsigned> BlockSizeUser for Backend<'_, R> {
     type BlockSize = U64;
 }
 
-impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<'_, R, K> {
-    type ParBlocksSize = U1;
+impl<R: Unsigned> ParBlocksSizeUser for Backend<'_, R> {
+    type ParBlocksSize = U4;
 }
 
-impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<'_, R, K> {
+impl<R: Unsigned> StreamCipherBackend for Backend<'_, R> {
     #[inline(always)]
     fn gen_ks_block(&mut self, block: &mut Block<Self>) {
-        let res = run_rounds::<R>(&self.0.state);
+        #[allow(unused_mut)]
+        let mut res = run_rounds::<R>(self.state);
 
-        self.0.set_block_pos(self.0.get_block_pos() + 1);
+        let ctr = ((self.state[8] as u64) | ((self.state[9] as u64) << 32)).wrapping_add(1);
+        self.state[8] = (ctr & 0xffff_ffff) as u32;
+        self.state[9] = ((ctr >> 32) & 0xffff_ffff) as u32;
 
         for (chunk, val) in block.chunks_exact_mut(4).zip(res.iter()) {
             chunk.copy_from_slice(&val.to_le_bytes());
         }
+
+        #[cfg(feature = "zeroize")]
+        res.zeroize();
+    }
+
+    /// Compute four blocks with their rounds interleaved. The blocks are
+    /// independent, which gives the compiler four times as many instructions
+    /// to schedule in parallel as the single-block loop. Partial batches fall
+    /// back to [`gen_ks_block`][Self::gen_ks_block].
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        let ctr = (self.state[8] as u64) | ((self.state[9] as u64) << 32);
+
+        // Word-major: `input[i][j]` is word `i` of block `j`
+        let mut input = [[0u32; PAR_BLOCKS]; STATE_WORDS];
+        for (row, s) in input.iter_mut().zip(self.state.iter()) {
+            *row = [*s; PAR_BLOCKS];
+        }
+        let (lo, hi) = input.split_at_mut(9);
+        for (j, (lo, hi)) in lo[8].iter_mut().zip(hi[0].iter_mut()).enumerate() {
+            let ctr = ctr.wrapping_add(j as u64);
+            *lo = (ctr & 0xffff_ffff) as u32;
+            *hi = ((ctr >> 32) & 0xffff_ffff) as u32;
+        }
+
+        let mut x = input;
+        for _ in 0..R::USIZE {
+            double_round_x4(&mut x);
+        }
+
+        for (i, (x, s)) in x.iter().zip(input.iter()).enumerate() {
+            for (j, block) in blocks.iter_mut().enumerate() {
+                let word = x[j].wrapping_add(s[j]);
+                block[4 * i..][..4].copy_from_slice(&word.to_le_bytes());
+            }
+        }
+
+        let ctr = ctr.wrapping_add(PAR_BLOCKS as u64);
+        self.state[8] = (ctr & 0xffff_ffff) as u32;
+        self.state[9] = ((ctr >> 32) & 0xffff_ffff) as u32;
+
+        #[cfg(feature = "zeroize")]
+        {
+            input.zeroize();
+            x.zeroize();
+        }
     }
 }
 
+/// The Salsa20 quarter-round on the state words `a`, `b`, `c` and `d`.
+///
+/// This is `quarterround(y0, y1, y2, y3)` from the [Salsa20 specification],
+/// with the words taken from `state[a]`, `state[b]`, `state[c]` and
+/// `state[d]` respectively and updated in place. It is exposed for testing,
+/// fuzzing and teaching purposes; encryption should use the ciphers.
+///
+/// # Example
+/// ```
+/// let mut state = [0u32; 16];
+/// state[0] = 1;
+/// salsa20::quarter_round(0, 1, 2, 3, &mut state);
+/// assert_eq!(state[..4], [0x08008145, 0x00000080, 0x00010200, 0x20500000]);
+/// ```
+///
+/// [Salsa20 specification]: https://cr.yp.to/snuffle/spec.pdf
 #[inline]
 #[allow(clippy::many_single_char_names)]
-pub(crate) fn quarter_round(
+pub fn quarter_round(a: usize, b: usize, c: usize, d: usize, state: &mut [u32; STATE_WORDS]) {
+    state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
+    state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
+    state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
+    state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+}
+
+/// The Salsa20 double round: a column round followed by a row round.
+///
+/// This is `doubleround` from the [Salsa20 specification], operating on the
+/// state words in canonical order. Salsa20/20 applies it ten times before
+/// adding the input state to produce a keystream block.
+///
+/// # Example
+/// ```
+/// let mut state = [0u32; 16];
+/// state[0] = 1;
+/// salsa20::double_round(&mut state);
+/// assert_eq!(state[..4], [0x8186a22d, 0x0040a284, 0x82479210, 0x06929051]);
+/// ```
+///
+/// [Salsa20 specification]: https://cr.yp.to/snuffle/spec.pdf
+#[inline]
+pub fn double_round(state: &mut [u32; STATE_WORDS]) {
+    // column rounds
+    quarter_round(0, 4, 8, 12, state);
+    quarter_round(5, 9, 13, 1, state);
+    quarter_round(10, 14, 2, 6, state);
+    quarter_round(15, 3, 7, 11, state);
+
+    // diagonal rounds
+    quarter_round(0, 1, 2, 3, state);
+    quarter_round(5, 6, 7, 4, state);
+    quarter_round(10, 11, 8, 9, state);
+    quarter_round(15, 12, 13, 14, state);
+}
+
+/// `x[t] ^= (x[y] + x[z]) <<< r` on the words of four blocks.
+#[inline(always)]
+fn step_x4(t: usize, y: usize, z: usize, r: u32, x: &mut [[u32; PAR_BLOCKS]; STATE_WORDS]) {
+    let (y, z) = (x[y], x[z]);
+    for ((t, y), z) in x[t].iter_mut().zip(y).zip(z) {
+        *t ^= y.wrapping_add(z).rotate_left(r);
+    }
+}
+
+/// [`quarter_round`] on the same words of four blocks at once.
+#[inline(always)]
+fn quarter_round_x4(
     a: usize,
     b: usize,
     c: usize,
     d: usize,
-    state: &mut [u32; STATE_WORDS],
+    x: &mut [[u32; PAR_BLOCKS]; STATE_WORDS],
 ) {
-    state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
-    state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
-    state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
-    state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+    step_x4(b, a, d, 7, x);
+    step_x4(c, b, a, 9, x);
+    step_x4(d, c, b, 13, x);
+    step_x4(a, d, c, 18, x);
+}
+
+/// [`double_round`] on four blocks at once.
+#[inline(always)]
+fn double_round_x4(x: &mut [[u32; PAR_BLOCKS]; STATE_WORDS]) {
+    // column rounds
+    quarter_round_x4(0, 4, 8, 12, x);
+    quarter_round_x4(5, 9, 13, 1, x);
+    quarter_round_x4(10, 14, 2, 6, x);
+    quarter_round_x4(15, 3, 7, 11, x);
+
+    // diagonal rounds
+    quarter_round_x4(0, 1, 2, 3, x);
+    quarter_round_x4(5, 6, 7, 4, x);
+    quarter_round_x4(10, 11, 8, 9, x);
+    quarter_round_x4(15, 12, 13, 14, x);
 }
 
 #[inline(always)]
+#[allow(dead_code)]
 fn run_rounds<R: Unsigned>(state: &[u32; STATE_WORDS]) -> [u32; STATE_WORDS] {
     let mut res = *state;
 
     for _ in 0..R::USIZE {
-        // column rounds
-        quarter_round(0, 4, 8, 12, &mut res);
-        quarter_round(5, 9, 13, 1, &mut res);
-        quarter_round(10, 14, 2, 6, &mut res);
-        quarter_round(15, 3, 7, 11, &mut res);
-
-        // diagonal rounds
-        quarter_round(0, 1, 2, 3, &mut res);
-        quarter_round(5, 6, 7, 4, &mut res);
-        quarter_round(10, 11, 8, 9, &mut res);
-        quarter_round(15, 12, 13, 14, &mut res);
+        double_round(&mut res);
     }
 
     for (s1, s0) in res.iter_mut().zip(state.iter()) {
@@ -1,7 +1,4 @@
-use crate::{
-    backends::soft::Backend as SoftBackend, Block, SalsaCore, StreamCipherClosure, Unsigned,
-    STATE_WORDS,
-};
+use crate::{Block, StreamCipherClosure, Unsigned, STATE_WORDS};
 use cipher::{
     array::ArraySize,
     consts::{U1, U64},
@@ -9,11 +6,16 @@ use cipher::{
 };
 use core::marker::PhantomData;
 
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
 #[cfg(target_arch = "x86")]
 use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
+// The AVX-512 backend only reuses `rounds`
+#[cfg_attr(target_feature = "avx512f", allow(dead_code))]
 #[inline]
 #[target_feature(enable = "sse2")]
 pub(crate) unsafe fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
@@ -34,17 +36,12 @@ where
         _pk: PhantomData,
     };
 
-    // The SSE2 backend only works for Salsa20/20. Any other variant will fallback to the soft backend.
-    if R::USIZE == 10 {
-        f.call(&mut backend);
-        state[8] = _mm_cvtsi128_si32(backend.v[2]) as u32;
-    } else {
-        f.call(&mut SoftBackend(&mut SalsaCore::<R, K> {
-            state: *state,
-            rounds: PhantomData,
-            key: PhantomData,
-        }));
-    }
+    f.call(&mut backend);
+    state[8] = _mm_cvtsi128_si32(backend.v[2]) as u32;
+    state[5] = _mm_cvtsi128_si32(_mm_shuffle_epi32(backend.v[1], 0b_00_00_00_01)) as u32;
+
+    #[cfg(feature = "zeroize")]
+    backend.v.zeroize();
 }
 
 struct Backend<R: Unsigned, K: ArraySize> {
@@ -65,21 +62,29 @@ impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
     #[inline(always)]
     fn gen_ks_block(&mut self, block: &mut Block<Self>) {
         unsafe {
-            let res = rounds::<R>(&self.v);
+            #[allow(unused_mut)]
+            let mut res = rounds::<R>(&self.v);
 
+            // The low counter word is lane 0 of `v[2]`, the high word is lane 1 of `v[1]`.
             self.v[2] = _mm_add_epi32(self.v[2], _mm_set_epi32(0, 0, 0, 1));
+            if _mm_cvtsi128_si32(self.v[2]) == 0 {
+                self.v[1] = _mm_add_epi32(self.v[1], _mm_set_epi32(0, 0, 1, 0));
+            }
             let block_ptr = block.as_mut_ptr() as *mut __m128i;
 
             for (i, v) in res.iter().enumerate() {
                 _mm_storeu_si128(block_ptr.add(i), *v);
             }
+
+            #[cfg(feature = "zeroize")]
+            res.zeroize();
         }
     }
 }
 
 #[inline]
 #[target_feature(enable = "sse2")]
-unsafe fn rounds<R: Unsigned>(v: &[__m128i; 4]) -> [__m128i; 4] {
+pub(crate) unsafe fn rounds<R: Unsigned>(v: &[__m128i; 4]) -> [__m128i; 4] {
     let mut res = *v;
 
     for _ in 0..R::USIZE {
@@ -99,8 +104,69 @@ unsafe fn rounds<R: Unsigned>(v: &[__m128i; 4]) -> [__m128i; 4] {
     res
 }
 
+/// Run the `R` double rounds of four HSalsa computations at once, without
+/// adding the input state afterwards.
+///
+/// `x[i][j]` is word `i` of computation `j`, so each vector holds the same
+/// state word of all four computations and the rounds need no lane shuffling.
+#[inline]
+#[target_feature(enable = "sse2")]
+pub(crate) unsafe fn hsalsa_rounds_x4<R: Unsigned>(x: &mut [[u32; 4]; STATE_WORDS]) {
+    let mut v = [_mm_setzero_si128(); STATE_WORDS];
+    for (v, x) in v.iter_mut().zip(x.iter()) {
+        *v = _mm_loadu_si128(x.as_ptr() as *const __m128i);
+    }
+
+    for _ in 0..R::USIZE {
+        // column rounds
+        quarter_round_x4(0, 4, 8, 12, &mut v);
+        quarter_round_x4(5, 9, 13, 1, &mut v);
+        quarter_round_x4(10, 14, 2, 6, &mut v);
+        quarter_round_x4(15, 3, 7, 11, &mut v);
+
+        // diagonal rounds
+        quarter_round_x4(0, 1, 2, 3, &mut v);
+        quarter_round_x4(5, 6, 7, 4, &mut v);
+        quarter_round_x4(10, 11, 8, 9, &mut v);
+        quarter_round_x4(15, 12, 13, 14, &mut v);
+    }
+
+    for (v, x) in v.iter().zip(x.iter_mut()) {
+        _mm_storeu_si128(x.as_mut_ptr() as *mut __m128i, *v);
+    }
+
+    #[cfg(feature = "zeroize")]
+    v.zeroize();
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn quarter_round_x4(a: usize, b: usize, c: usize, d: usize, v: &mut [__m128i; STATE_WORDS]) {
+    let mut t_sum: __m128i;
+
+    t_sum = _mm_add_epi32(v[a], v[d]);
+    v[b] = _mm_xor_si128(v[b], _mm_slli_epi32(t_sum, 7));
+    v[b] = _mm_xor_si128(v[b], _mm_srli_epi32(t_sum, 25));
+
+    t_sum = _mm_add_epi32(v[b], v[a]);
+    v[c] = _mm_xor_si128(v[c], _mm_slli_epi32(t_sum, 9));
+    v[c] = _mm_xor_si128(v[c], _mm_srli_epi32(t_sum, 23));
+
+    t_sum = _mm_add_epi32(v[c], v[b]);
+    v[d] = _mm_xor_si128(v[d], _mm_slli_epi32(t_sum, 13));
+    v[d] = _mm_xor_si128(v[d], _mm_srli_epi32(t_sum, 19));
+
+    t_sum = _mm_add_epi32(v[d], v[c]);
+    v[a] = _mm_xor_si128(v[a], _mm_slli_epi32(t_sum, 18));
+    v[a] = _mm_xor_si128(v[a], _mm_srli_epi32(t_sum, 14));
+}
+
 /// The Salsa20 doubleround function for SSE2.
 ///
+/// All rotates are shift-or pairs. Unlike ChaCha, whose 8 and 16-bit rotates
+/// can use the SSSE3 byte shuffle `pshufb`, Salsa20 only rotates by 7, 9, 13
+/// and 18 bits, none of which moves whole bytes, so there is no SSSE3 variant.
+///
 /// https://users.rust-lang.org/t/can-the-compiler-infer-sse-instructions/59976
 #[inline]
 #[target_feature(enable = "sse2")]
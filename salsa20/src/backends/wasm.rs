@@ -0,0 +1,171 @@
+//! simd128-optimized implementation for wasm32.
+//!
+//! Like the NEON backend, four blocks are processed in parallel with each
+//! vector holding the same state word of all four blocks.
+
+use crate::{
+    backends::soft::Backend as SoftBackend, Block, StreamCipherClosure, Unsigned, STATE_WORDS,
+};
+use cipher::{
+    array::ArraySize,
+    consts::{U4, U64},
+    BlockSizeUser, ParBlocks, ParBlocksSizeUser, StreamCipherBackend,
+};
+use core::{arch::wasm32::*, marker::PhantomData};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+/// Number of blocks processed in parallel.
+const PAR_BLOCKS: usize = 4;
+
+#[inline]
+pub(crate) fn inner<R, K, F>(state: &mut [u32; STATE_WORDS], f: F)
+where
+    R: Unsigned,
+    K: ArraySize,
+    F: StreamCipherClosure<BlockSize = U64>,
+{
+    let mut v = [u32x4_splat(0); STATE_WORDS];
+    for (v, s) in v.iter_mut().zip(state.iter()) {
+        *v = u32x4_splat(*s);
+    }
+    let mut backend = Backend::<R, K> {
+        v,
+        ctr: (state[8] as u64) | ((state[9] as u64) << 32),
+        state: *state,
+        _pd: PhantomData,
+        _pk: PhantomData,
+    };
+
+    f.call(&mut backend);
+
+    state[8] = (backend.ctr & 0xffff_ffff) as u32;
+    state[9] = ((backend.ctr >> 32) & 0xffff_ffff) as u32;
+
+    #[cfg(feature = "zeroize")]
+    backend.state.zeroize();
+}
+
+struct Backend<R: Unsigned, K: ArraySize> {
+    /// State words broadcast to all lanes. The counter words are overwritten
+    /// with per-block values on every call.
+    v: [v128; STATE_WORDS],
+    /// 64-bit block counter.
+    ctr: u64,
+    /// State words in canonical order for the single-block path.
+    state: [u32; STATE_WORDS],
+    _pd: PhantomData<R>,
+    _pk: PhantomData<K>,
+}
+
+impl<R: Unsigned, K: ArraySize> BlockSizeUser for Backend<R, K> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned, K: ArraySize> ParBlocksSizeUser for Backend<R, K> {
+    type ParBlocksSize = U4;
+}
+
+impl<R: Unsigned, K: ArraySize> StreamCipherBackend for Backend<R, K> {
+    /// Single blocks are computed with the scalar rounds, which is cheaper
+    /// than computing all four lanes and discarding three of them.
+    #[inline(always)]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        self.state[8] = (self.ctr & 0xffff_ffff) as u32;
+        self.state[9] = ((self.ctr >> 32) & 0xffff_ffff) as u32;
+        SoftBackend::<R>::new(&mut self.state).gen_ks_block(block);
+        self.ctr = self.ctr.wrapping_add(1);
+    }
+
+    #[inline(always)]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        let ctrs: [u64; PAR_BLOCKS] = core::array::from_fn(|i| self.ctr.wrapping_add(i as u64));
+        self.ctr = self.ctr.wrapping_add(PAR_BLOCKS as u64);
+
+        let mut state = self.v;
+        state[8] = u32x4(
+            ctrs[0] as u32,
+            ctrs[1] as u32,
+            ctrs[2] as u32,
+            ctrs[3] as u32,
+        );
+        state[9] = u32x4(
+            (ctrs[0] >> 32) as u32,
+            (ctrs[1] >> 32) as u32,
+            (ctrs[2] >> 32) as u32,
+            (ctrs[3] >> 32) as u32,
+        );
+
+        let mut x = state;
+        for _ in 0..R::USIZE {
+            double_round(&mut x);
+        }
+        for (x, s) in x.iter_mut().zip(state.iter()) {
+            *x = u32x4_add(*x, *s);
+        }
+
+        // Transpose each group of four words from word-major to block-major order
+        for (i, w) in x.chunks_exact(4).enumerate() {
+            let t0 = i32x4_shuffle::<0, 4, 1, 5>(w[0], w[1]);
+            let t1 = i32x4_shuffle::<2, 6, 3, 7>(w[0], w[1]);
+            let t2 = i32x4_shuffle::<0, 4, 1, 5>(w[2], w[3]);
+            let t3 = i32x4_shuffle::<2, 6, 3, 7>(w[2], w[3]);
+
+            let rows = [
+                i64x2_shuffle::<0, 2>(t0, t2),
+                i64x2_shuffle::<1, 3>(t0, t2),
+                i64x2_shuffle::<0, 2>(t1, t3),
+                i64x2_shuffle::<1, 3>(t1, t3),
+            ];
+            for (block, row) in blocks.iter_mut().zip(rows.iter()) {
+                let ptr = block[16 * i..].as_mut_ptr() as *mut v128;
+                unsafe { v128_store(ptr, *row) };
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn gen_tail_blocks(&mut self, blocks: &mut [Block<Self>]) {
+        if let [block] = blocks {
+            return self.gen_ks_block(block);
+        }
+        let n = blocks.len();
+        let mut par = ParBlocks::<Self>::default();
+        self.gen_par_ks_blocks(&mut par);
+        blocks.copy_from_slice(&par[..n]);
+        #[cfg(feature = "zeroize")]
+        for block in par.iter_mut() {
+            block[..].zeroize();
+        }
+        self.ctr = self.ctr.wrapping_sub((PAR_BLOCKS - n) as u64);
+    }
+}
+
+#[inline(always)]
+fn rotate_left(v: v128, r: u32) -> v128 {
+    v128_or(u32x4_shl(v, r), u32x4_shr(v, 32 - r))
+}
+
+#[inline(always)]
+fn quarter_round(a: usize, b: usize, c: usize, d: usize, x: &mut [v128; STATE_WORDS]) {
+    x[b] = v128_xor(x[b], rotate_left(u32x4_add(x[a], x[d]), 7));
+    x[c] = v128_xor(x[c], rotate_left(u32x4_add(x[b], x[a]), 9));
+    x[d] = v128_xor(x[d], rotate_left(u32x4_add(x[c], x[b]), 13));
+    x[a] = v128_xor(x[a], rotate_left(u32x4_add(x[d], x[c]), 18));
+}
+
+#[inline(always)]
+fn double_round(x: &mut [v128; STATE_WORDS]) {
+    // column rounds
+    quarter_round(0, 4, 8, 12, x);
+    quarter_round(5, 9, 13, 1, x);
+    quarter_round(10, 14, 2, 6, x);
+    quarter_round(15, 3, 7, 11, x);
+
+    // diagonal rounds
+    quarter_round(0, 1, 2, 3, x);
+    quarter_round(5, 6, 7, 4, x);
+    quarter_round(10, 11, 8, 9, x);
+    quarter_round(15, 12, 13, 14, x);
+}
@@ -0,0 +1,66 @@
+//! Fallible construction of [`SalsaCore`] from byte slices
+
+use super::SalsaCore;
+use cipher::{
+    array::{typenum::Unsigned, ArraySize},
+    InvalidLength, StreamCipherSeekCore,
+};
+use core::marker::PhantomData;
+
+/// Builder for [`SalsaCore`] taking the key and nonce as byte slices, e.g. a
+/// `Vec<u8>` read from a file, instead of [`Array`][cipher::array::Array]
+/// references.
+///
+/// The lengths are validated by [`build`][Self::build], which returns
+/// [`InvalidLength`] instead of panicking, see [`SalsaCore::try_new`].
+///
+/// # Example
+/// ```
+/// use salsa20::SalsaCoreBuilder;
+/// use salsa20::cipher::{consts::{U10, U32}, StreamCipherSeekCore};
+///
+/// let key = vec![0x42; 32];
+/// let nonce = vec![0x24; 8];
+///
+/// let core = SalsaCoreBuilder::<U10, U32>::new(&key, &nonce)
+///     .counter(5)
+///     .build()
+///     .unwrap();
+/// assert_eq!(core.get_block_pos(), 5);
+///
+/// assert!(SalsaCoreBuilder::<U10, U32>::new(&key[..16], &nonce).build().is_err());
+/// ```
+#[derive(Clone, Copy)]
+pub struct SalsaCoreBuilder<'a, R: Unsigned, K: ArraySize> {
+    key: &'a [u8],
+    nonce: &'a [u8],
+    counter: u64,
+    _pd: PhantomData<(R, K)>,
+}
+
+impl<'a, R: Unsigned, K: ArraySize> SalsaCoreBuilder<'a, R, K> {
+    /// Create new builder for a core with the given key and nonce, starting
+    /// at block position 0.
+    pub fn new(key: &'a [u8], nonce: &'a [u8]) -> Self {
+        Self {
+            key,
+            nonce,
+            counter: 0,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Start the keystream at block position `counter` instead of 0, see
+    /// [`SalsaCore::new_with_counter`].
+    pub fn counter(mut self, counter: u64) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Validate the key and nonce lengths and create the core.
+    pub fn build(self) -> Result<SalsaCore<R, K>, InvalidLength> {
+        let mut core = SalsaCore::try_new(self.key, self.nonce)?;
+        core.set_block_pos(self.counter);
+        Ok(core)
+    }
+}
@@ -0,0 +1,71 @@
+//! Block counter widths, see the `C` parameter of [`SalsaCore`][crate::SalsaCore].
+
+use cipher::{
+    consts::{U4, U8},
+    typenum::Unsigned,
+    StreamCipherCounter,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Size of the block counter in bytes, [`U8`] or [`U4`].
+///
+/// With [`U8`], as defined by Salsa20, state words 8 and 9 form a 64-bit
+/// block counter. With [`U4`] only word 8 is the counter and word 9 keeps
+/// its value, as in systems which treat Salsa20 as having a 32-bit counter.
+/// The keystream is the same as long as the counter does not carry into
+/// word 9.
+///
+/// This trait is sealed and can not be implemented outside of this crate.
+pub trait CounterSize: Unsigned + sealed::Sealed {
+    /// Block position type of [`StreamCipherSeekCore`][cipher::StreamCipherSeekCore].
+    type Counter: StreamCipherCounter + Copy;
+
+    /// Block position at which the counter would wrap around. Its block is
+    /// never generated, see [`SalsaCore::remaining_blocks`][crate::SalsaCore::remaining_blocks].
+    const MAX_POS: u64;
+
+    /// Convert a block position into the counter type.
+    fn from_pos(pos: u64) -> Self::Counter;
+
+    /// Convert a counter value into a block position.
+    fn to_pos(ctr: Self::Counter) -> u64;
+}
+
+impl sealed::Sealed for U8 {}
+
+impl CounterSize for U8 {
+    type Counter = u64;
+
+    const MAX_POS: u64 = u64::MAX;
+
+    #[inline(always)]
+    fn from_pos(pos: u64) -> u64 {
+        pos
+    }
+
+    #[inline(always)]
+    fn to_pos(ctr: u64) -> u64 {
+        ctr
+    }
+}
+
+impl sealed::Sealed for U4 {}
+
+impl CounterSize for U4 {
+    type Counter = u32;
+
+    const MAX_POS: u64 = u32::MAX as u64;
+
+    #[inline(always)]
+    fn from_pos(pos: u64) -> u32 {
+        pos as u32
+    }
+
+    #[inline(always)]
+    fn to_pos(ctr: u32) -> u64 {
+        ctr.into()
+    }
+}
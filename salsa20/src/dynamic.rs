@@ -0,0 +1,138 @@
+//! Salsa variant selected at runtime.
+
+use crate::{Key, Nonce, Salsa20};
+use cipher::{
+    consts::U32, InOutBuf, KeyIvInit, OverflowError, SeekNum, StreamCipher, StreamCipherError,
+    StreamCipherSeek,
+};
+use core::fmt;
+
+#[cfg(feature = "reduced-round")]
+use crate::{Salsa12, Salsa8};
+
+/// Salsa variant of a [`DynSalsa`] cipher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SalsaVariant {
+    /// [`Salsa8`][crate::Salsa8] (*not recommended*)
+    #[cfg(feature = "reduced-round")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+    Salsa8,
+    /// [`Salsa12`][crate::Salsa12] (*not recommended*)
+    #[cfg(feature = "reduced-round")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+    Salsa12,
+    /// [`Salsa20`] (**recommended**)
+    Salsa20,
+}
+
+impl SalsaVariant {
+    /// Get the number of rounds, e.g. 20 for Salsa20/20.
+    pub const fn rounds(self) -> usize {
+        match self {
+            #[cfg(feature = "reduced-round")]
+            Self::Salsa8 => 8,
+            #[cfg(feature = "reduced-round")]
+            Self::Salsa12 => 12,
+            Self::Salsa20 => 20,
+        }
+    }
+}
+
+/// Salsa stream cipher whose variant is selected at runtime, e.g. from a
+/// configuration value.
+///
+/// The round count of the other ciphers of this crate is a type parameter,
+/// so choosing it at runtime would require every code path to be generic.
+/// This type dispatches to the concrete cipher of the [`SalsaVariant`]
+/// instead, at the cost of a branch per call.
+///
+/// # Example
+/// ```
+/// use salsa20::{DynSalsa, SalsaVariant};
+/// use salsa20::cipher::StreamCipher;
+///
+/// let mut cipher = DynSalsa::new(SalsaVariant::Salsa20, &[0x42; 32].into(), &[0x24; 8].into());
+/// let mut buf = [0x00, 0x01, 0x02, 0x03];
+/// cipher.apply_keystream(&mut buf);
+/// assert_eq!(buf, [0x85, 0x84, 0x3c, 0xc5]);
+/// ```
+#[derive(Clone)]
+pub struct DynSalsa(Inner);
+
+#[derive(Clone)]
+enum Inner {
+    #[cfg(feature = "reduced-round")]
+    Salsa8(Salsa8),
+    #[cfg(feature = "reduced-round")]
+    Salsa12(Salsa12),
+    Salsa20(Salsa20),
+}
+
+/// Evaluate `$e` with `$c` bound to the concrete cipher.
+macro_rules! dispatch {
+    ($inner:expr, $c:ident => $e:expr) => {
+        match $inner {
+            #[cfg(feature = "reduced-round")]
+            Inner::Salsa8($c) => $e,
+            #[cfg(feature = "reduced-round")]
+            Inner::Salsa12($c) => $e,
+            Inner::Salsa20($c) => $e,
+        }
+    };
+}
+
+impl DynSalsa {
+    /// Create new cipher of the given `variant`.
+    pub fn new(variant: SalsaVariant, key: &Key<U32>, nonce: &Nonce) -> Self {
+        Self(match variant {
+            #[cfg(feature = "reduced-round")]
+            SalsaVariant::Salsa8 => Inner::Salsa8(Salsa8::new(key, nonce)),
+            #[cfg(feature = "reduced-round")]
+            SalsaVariant::Salsa12 => Inner::Salsa12(Salsa12::new(key, nonce)),
+            SalsaVariant::Salsa20 => Inner::Salsa20(Salsa20::new(key, nonce)),
+        })
+    }
+
+    /// Get the variant of the cipher.
+    pub fn variant(&self) -> SalsaVariant {
+        match self.0 {
+            #[cfg(feature = "reduced-round")]
+            Inner::Salsa8(_) => SalsaVariant::Salsa8,
+            #[cfg(feature = "reduced-round")]
+            Inner::Salsa12(_) => SalsaVariant::Salsa12,
+            Inner::Salsa20(_) => SalsaVariant::Salsa20,
+        }
+    }
+}
+
+/// The key-derived state is never printed.
+impl fmt::Debug for DynSalsa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynSalsa")
+            .field("variant", &self.variant())
+            .finish_non_exhaustive()
+    }
+}
+
+impl StreamCipher for DynSalsa {
+    #[inline]
+    fn try_apply_keystream_inout(
+        &mut self,
+        buf: InOutBuf<'_, '_, u8>,
+    ) -> Result<(), StreamCipherError> {
+        dispatch!(&mut self.0, c => c.try_apply_keystream_inout(buf))
+    }
+}
+
+impl StreamCipherSeek for DynSalsa {
+    #[inline]
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        dispatch!(&self.0, c => c.try_current_pos())
+    }
+
+    #[inline]
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), StreamCipherError> {
+        dispatch!(&mut self.0, c => c.try_seek(pos))
+    }
+}
@@ -0,0 +1,186 @@
+//! Length-prefixed framing of encrypted chunks for streaming transports.
+
+use crate::Salsa20;
+use cipher::StreamCipher;
+use std::{
+    io::{self, Read, Write},
+    vec::Vec,
+};
+
+/// Size of the stack buffer used to encrypt a frame before passing it to the
+/// inner writer.
+const WRITE_BUF_SIZE: usize = 4096;
+
+/// Writer of **unauthenticated** frames: each frame is a 4-byte little-endian
+/// length prefix in plain text, followed by the data encrypted with `C`.
+///
+/// The keystream continues across frames, so frame `n` is encrypted with the
+/// keystream following that of frame `n - 1`. An
+/// [`UnauthenticatedFrameReader`] with a cipher in the same state reads the
+/// frames back, with both sides staying counter-synchronized as long as every
+/// frame is delivered.
+///
+/// # ⚠️ Security Warning
+/// The frames are neither authenticated nor protected against reordering or
+/// truncation: an attacker can flip bits of the data, change the length
+/// prefixes and drop frames undetected. Only use this framing below a layer
+/// which authenticates the data.
+///
+/// # Example
+/// ```
+/// use salsa20::{Salsa20, UnauthenticatedFrameReader, UnauthenticatedFrameWriter};
+/// use salsa20::cipher::KeyIvInit;
+///
+/// let (key, nonce) = ([0x42; 32].into(), [0x24; 8].into());
+///
+/// let mut writer = UnauthenticatedFrameWriter::new(Vec::new(), Salsa20::new(&key, &nonce));
+/// writer.write_frame(b"hello").unwrap();
+/// writer.write_frame(b"world").unwrap();
+/// let (transport, _) = writer.into_inner();
+///
+/// let mut reader = UnauthenticatedFrameReader::new(&transport[..], Salsa20::new(&key, &nonce));
+/// assert_eq!(reader.read_frame().unwrap().unwrap(), b"hello");
+/// assert_eq!(reader.read_frame().unwrap().unwrap(), b"world");
+/// assert!(reader.read_frame().unwrap().is_none());
+/// ```
+pub struct UnauthenticatedFrameWriter<W, C = Salsa20> {
+    inner: W,
+    cipher: C,
+}
+
+impl<W, C> UnauthenticatedFrameWriter<W, C> {
+    /// Create new writer which writes frames encrypted with `cipher` to
+    /// `inner`.
+    pub fn new(inner: W, cipher: C) -> Self {
+        Self { inner, cipher }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a reference to the cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Return the inner writer and the cipher.
+    pub fn into_inner(self) -> (W, C) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<W: Write, C: StreamCipher> UnauthenticatedFrameWriter<W, C> {
+    /// Encrypt `data` and write it as one frame.
+    ///
+    /// Empty frames are allowed and consume no keystream.
+    ///
+    /// # Errors
+    /// If `data` is longer than `u32::MAX` bytes, the keystream is exhausted
+    /// or the inner writer fails. The stream can not be continued after an
+    /// error, as the frame may have been written partially.
+    pub fn write_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too long"))?;
+        self.inner.write_all(&len.to_le_bytes())?;
+
+        let mut tmp = [0u8; WRITE_BUF_SIZE];
+        for chunk in data.chunks(WRITE_BUF_SIZE) {
+            let tmp = &mut tmp[..chunk.len()];
+            tmp.copy_from_slice(chunk);
+            self.cipher
+                .try_apply_keystream(tmp)
+                .map_err(|_| io::Error::other("keystream exhausted"))?;
+            self.inner.write_all(tmp)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the inner writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader of the **unauthenticated** frames written by an
+/// [`UnauthenticatedFrameWriter`].
+///
+/// # ⚠️ Security Warning
+/// The data is returned without any integrity check, see
+/// [`UnauthenticatedFrameWriter`].
+pub struct UnauthenticatedFrameReader<R, C = Salsa20> {
+    inner: R,
+    cipher: C,
+}
+
+impl<R, C> UnauthenticatedFrameReader<R, C> {
+    /// Create new reader which reads frames from `inner` and decrypts them
+    /// with `cipher`.
+    pub fn new(inner: R, cipher: C) -> Self {
+        Self { inner, cipher }
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a reference to the cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Return the inner reader and the cipher.
+    pub fn into_inner(self) -> (R, C) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<R: Read, C: StreamCipher> UnauthenticatedFrameReader<R, C> {
+    /// Read and decrypt the next frame.
+    ///
+    /// Returns `None` if the inner reader is at its end before the first byte
+    /// of a frame. The buffer grows with the data actually received, so a
+    /// forged length prefix does not cause a large allocation by itself.
+    ///
+    /// # Errors
+    /// [`io::ErrorKind::UnexpectedEof`] if the inner reader ends within a
+    /// frame, or if the keystream is exhausted or the inner reader fails.
+    pub fn read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut prefix = [0u8; 4];
+        let n = read_full(&mut self.inner, &mut prefix)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if n != prefix.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let len = u32::from_le_bytes(prefix);
+
+        let mut data = Vec::new();
+        (&mut self.inner).take(len.into()).read_to_end(&mut data)?;
+        if data.len() as u64 != u64::from(len) {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.cipher
+            .try_apply_keystream(&mut data)
+            .map_err(|_| io::Error::other("keystream exhausted"))?;
+        Ok(Some(data))
+    }
+}
+
+/// Fill `buf` from `reader` unless it ends, and return the number of bytes
+/// read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
@@ -0,0 +1,45 @@
+//! Hooks for the differential fuzz target in `fuzz/`.
+//!
+//! `cargo fuzz` builds all crates with `--cfg fuzzing`. Only then the
+//! software backend is reachable next to the SIMD backend selected for the
+//! target, so both can be compared in the same process.
+
+use crate::{backends, Block, SalsaCore, Unsigned};
+use cipher::{array::ArraySize, consts::U8, ParBlocks, StreamCipherBackend};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+impl<R: Unsigned, K: ArraySize> SalsaCore<R, K, U8> {
+    /// Same as [`generate_keystream`][Self::generate_keystream], but always
+    /// computed by the software backend.
+    #[doc(hidden)]
+    pub fn soft_generate_keystream(&mut self, buf: &mut [u8]) -> usize {
+        type Soft<'a, R> = backends::soft::Backend<'a, R>;
+
+        let n = buf.len();
+        let mut state = self.canonical_state();
+        let mut backend = Soft::<R>::new(&mut state);
+
+        let (blocks, tail) = Block::<Self>::slice_as_chunks_mut(buf);
+        let (par, blocks) = ParBlocks::<Soft<'_, R>>::slice_as_chunks_mut(blocks);
+        for par in par {
+            backend.gen_par_ks_blocks(par);
+        }
+        for block in blocks {
+            backend.gen_ks_block(block);
+        }
+        if !tail.is_empty() {
+            let mut block = Block::<Self>::default();
+            backend.gen_ks_block(&mut block);
+            tail.copy_from_slice(&block[..tail.len()]);
+        }
+
+        self.state[8] = state[8];
+        self.state[backends::CTR_HI] = state[9];
+
+        #[cfg(feature = "zeroize")]
+        state.zeroize();
+        n
+    }
+}
@@ -0,0 +1,139 @@
+//! [`std::io`] wrappers which apply the keystream to the data passing through.
+
+use crate::Salsa20;
+use cipher::{StreamCipher, StreamCipherSeek};
+use std::io::{self, Read, Write};
+
+/// Size of the stack buffer used to encrypt data before passing it to the
+/// inner writer.
+const WRITE_BUF_SIZE: usize = 4096;
+
+/// Reader which applies the keystream of `C` to the data read from the inner
+/// reader.
+///
+/// # Example
+/// ```
+/// use salsa20::{Salsa20, SalsaReader};
+/// use salsa20::cipher::KeyIvInit;
+/// use std::io::Read;
+///
+/// let ciphertext: &[u8] = &[0x85, 0x84, 0x3c, 0xc5];
+/// let cipher = Salsa20::new(&[0x42; 32].into(), &[0x24; 8].into());
+///
+/// let mut plaintext = Vec::new();
+/// SalsaReader::new(ciphertext, cipher).read_to_end(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, [0x00, 0x01, 0x02, 0x03]);
+/// ```
+pub struct SalsaReader<R, C = Salsa20> {
+    inner: R,
+    cipher: C,
+}
+
+impl<R, C> SalsaReader<R, C> {
+    /// Create new reader which applies the keystream of `cipher` to `inner`.
+    pub fn new(inner: R, cipher: C) -> Self {
+        Self { inner, cipher }
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a reference to the cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Return the inner reader and the cipher.
+    pub fn into_inner(self) -> (R, C) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<R: Read, C: StreamCipher> Read for SalsaReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.cipher
+            .try_apply_keystream(&mut buf[..n])
+            .map_err(|_| io::Error::other("keystream exhausted"))?;
+        Ok(n)
+    }
+}
+
+/// Writer which applies the keystream of `C` to the data before passing it
+/// to the inner writer.
+///
+/// The keystream is applied with a copy of the cipher, and the cipher only
+/// advances over the bytes the inner writer accepted. So the keystream
+/// position always matches the number of bytes reported as written, and the
+/// keystream of rejected bytes is never used.
+///
+/// # Example
+/// ```
+/// use salsa20::{Salsa20, SalsaWriter};
+/// use salsa20::cipher::KeyIvInit;
+/// use std::io::Write;
+///
+/// let cipher = Salsa20::new(&[0x42; 32].into(), &[0x24; 8].into());
+///
+/// let mut writer = SalsaWriter::new(Vec::new(), cipher);
+/// writer.write_all(&[0x00, 0x01, 0x02, 0x03]).unwrap();
+/// let (ciphertext, _) = writer.into_inner();
+/// assert_eq!(ciphertext, [0x85, 0x84, 0x3c, 0xc5]);
+/// ```
+pub struct SalsaWriter<W, C = Salsa20> {
+    inner: W,
+    cipher: C,
+}
+
+impl<W, C> SalsaWriter<W, C> {
+    /// Create new writer which applies the keystream of `cipher` to the data
+    /// written to `inner`.
+    pub fn new(inner: W, cipher: C) -> Self {
+        Self { inner, cipher }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a reference to the cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Return the inner writer and the cipher.
+    pub fn into_inner(self) -> (W, C) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<W: Write, C: StreamCipher + StreamCipherSeek + Clone> Write for SalsaWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut tmp = [0u8; WRITE_BUF_SIZE];
+        let len = buf.len().min(WRITE_BUF_SIZE);
+        let tmp = &mut tmp[..len];
+        tmp.copy_from_slice(&buf[..len]);
+
+        let mut cipher = self.cipher.clone();
+        cipher
+            .try_apply_keystream(tmp)
+            .map_err(|_| io::Error::other("keystream exhausted"))?;
+
+        let written = self.inner.write(tmp)?;
+        if written == len {
+            self.cipher = cipher;
+        } else {
+            // Skip only the bytes the inner writer accepted
+            let pos: u64 = self.cipher.current_pos();
+            self.cipher.seek(pos + written as u64);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
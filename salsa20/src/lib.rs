@@ -46,65 +46,343 @@
 //!
 //! let ciphertext = buffer.clone();
 //!
-//! // Salsa ciphers support seeking
-//! cipher.seek(0u32);
-//!
-//! // decrypt ciphertext by applying keystream again
+//! // decrypt ciphertext by applying the keystream of a new instance
+//! let mut cipher = Salsa20::new(&key.into(), &nonce.into());
 //! cipher.apply_keystream(&mut buffer);
 //! assert_eq!(buffer, plaintext);
 //!
-//! // stream ciphers can be used with streaming messages
+//! // Salsa ciphers support seeking
+//! let mut cipher = Salsa20::new(&key.into(), &nonce.into());
 //! cipher.seek(0u32);
+//!
+//! // stream ciphers can be used with streaming messages
 //! for chunk in buffer.chunks_mut(3) {
 //!     cipher.apply_keystream(chunk);
 //! }
 //! assert_eq!(buffer, ciphertext);
 //! ```
 //!
-//! Salsa20 will run the SSE2 backend in x86(-64) targets for Salsa20/20 variant.
-//! Other variants will fallback to the software backend.
+//! On x86(-64) targets the backend is selected at runtime: the AVX2 backend
+//! (four blocks at a time) is used if the CPU supports it, then the SSE2 backend,
+//! and the software backend otherwise. The SIMD code is only executed after
+//! the CPU was found to support it, so targets without SSE2 in their baseline,
+//! such as `i586-unknown-linux-gnu`, run on any CPU. If the `avx512f` target feature is enabled
+//! at compile time (e.g. with `RUSTFLAGS="-C target-feature=+avx512f"`), the
+//! AVX-512 backend (eight blocks at a time) is used unconditionally. The AVX-512
+//! backend requires Rust 1.89 or newer. All SIMD backends support every variant,
+//! including XSalsa20 and the reduced-round ciphers.
+//!
+//! On aarch64 targets the NEON backend is used, as is the simd128 backend on
+//! wasm32 targets built with the `simd128` target feature. On 32-bit ARM
+//! targets built with the `neon` target feature a NEON backend can be enabled
+//! with the `salsa20_arm_neon` configuration flag, as can an AltiVec backend
+//! on PowerPC64 targets with the `salsa20_altivec` flag and an LSX backend on
+//! LoongArch64 targets with the `salsa20_loongarch` flag. The LSX backend
+//! requires the `lsx` target feature, which is enabled by default on the
+//! `loongarch64-unknown-linux-*` targets. The 256-bit LASX extension is not
+//! used.
+//!
+//! On riscv64 targets built with the `v` target feature (e.g. with
+//! `RUSTFLAGS="-C target-feature=+v"`) the RVV backend is used. It adapts to
+//! the vector length of the CPU at runtime, processing `VLEN / 32` blocks at a
+//! time. The `v` target feature is not stable yet, so this requires a nightly
+//! compiler.
+//!
+//! # Reduced-Round Variants
+//!
+//! The reduced-round variants [`Salsa8`], [`Salsa12`], [`XSalsa8`] and
+//! [`XSalsa12`] (and the corresponding RNG, 128-bit nonce and HSalsa aliases)
+//! trade security margin for speed and are only available with the
+//! `reduced-round` feature, so that they can not be used by accident. The
+//! generic [`SalsaCore`] and [`XSalsaCore`] accept any number of rounds
+//! regardless of the feature.
+//!
+//! # Keystream Reuse Detection
+//!
+//! Generating the keystream for the same block position twice with the
+//! same key and nonce, e.g. by seeking back and encrypting other data,
+//! breaks the confidentiality of both messages. With the `paranoid` feature
+//! enabled, debug builds panic if a cipher instance generates the keystream
+//! of a block it already used. This also rejects decrypting data with the
+//! instance which encrypted it, so use a fresh instance for that. A block
+//! the cipher used only part of, e.g. because it seeked within it, may be
+//! generated again, so reuse within it is not detected. Only the most
+//! recently used contiguous range of blocks is tracked, and release builds
+//! do not check anything. `SalsaRng` is exempt, as replaying its
+//! output by moving the word position back is intended.
+//!
+//! To track down reuse of older ranges, debug builds with the `paranoid`
+//! feature also audit all generated block positions, see
+//! `SalsaCore::consumed_positions`.
+//!
+//! # Parallel Processing
+//!
+//! With the `rayon` feature [`SalsaCore`] and [`XSalsaCore`] provide a
+//! `par_apply_keystream` method, which splits large buffers into chunks and
+//! applies the keystream on the [rayon] thread pool. As every block of the
+//! keystream can be computed independently, this scales with the number of
+//! cores for buffers of several megabytes.
+//!
+//! # Configuration Flags
+//!
+//! You can modify crate using the following configuration flags:
+//!
+//! - `salsa20_force_soft`: force software backend on all targets.
+//! - `salsa20_force_sse2`: force SSE2 backend on x86/x86_64 targets.
+//!   Requires enabled SSE2 target feature. Ignored on non-x86(-64) targets.
+//! - `salsa20_arm_neon`: enable NEON backend on 32-bit ARM targets.
+//!   Requires enabled NEON target feature and a nightly compiler, since the
+//!   32-bit ARM NEON intrinsics are not stable yet. Ignored on other targets.
+//! - `salsa20_altivec`: enable AltiVec backend on PowerPC64 targets.
+//!   Requires enabled AltiVec target feature and a nightly compiler, since the
+//!   PowerPC vector intrinsics are not stable yet. Ignored on other targets.
+//! - `salsa20_loongarch`: enable LSX backend on LoongArch64 targets.
+//!   Requires enabled LSX target feature and a nightly compiler, since the
+//!   LoongArch intrinsics are not stable yet. Ignored on other targets.
+//!
+//! The flags can be enabled using `RUSTFLAGS` environmental variable
+//! (e.g. `RUSTFLAGS="--cfg salsa20_force_soft"`) or by modifying `.cargo/config`.
+//!
+//! You SHOULD NOT enable several `force` flags simultaneously.
+//!
+//! # Side Channels
+//!
+//! Salsa20 only uses 32-bit additions, XORs and rotations by constant
+//! amounts, so all backends are free of secret-dependent branches, table
+//! lookups and memory accesses. The software backend is plain Rust on `u32`
+//! words; it relies on the target executing these operations in constant
+//! time, which holds for all common CPUs including microcontrollers with a
+//! barrel shifter. On cores where shifts take a number of cycles depending on
+//! the shift amount, the amounts are still constants and thus independent of
+//! the key. Users with strict requirements can select the software backend
+//! with the `salsa20_force_soft` flag and review its generated code.
+//!
+//! # Zeroization
+//!
+//! With the `zeroize` feature enabled the cipher state is zeroized on drop.
+//! Additionally the SSE2 and software backends zeroize their temporary
+//! working copies of the state and of the keystream before returning. This
+//! is best-effort: the compiler may still leave copies of intermediate
+//! values in registers or spilled on the stack.
+//!
+//! Code which creates and drops many short-lived ciphers can wrap the core
+//! in [`ManuallyZeroize`] to skip the zeroization on drop and scrub the state
+//! explicitly instead. The key-derived state of a wrapped core which is
+//! dropped without being zeroized stays in memory.
+//!
+//! The key and nonce passed to the constructors are not zeroized by the
+//! cipher. Keep them in a [`SecretKey`] and [`SecretNonce`] to zeroize them
+//! on drop as well.
 //!
 //! [Salsa]: https://en.wikipedia.org/wiki/Salsa20
+//! [rayon]: https://docs.rs/rayon
 
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(
+    all(target_arch = "arm", target_feature = "neon", salsa20_arm_neon),
+    feature(stdarch_arm_neon_intrinsics)
+)]
+#![cfg_attr(
+    all(target_arch = "powerpc64", target_feature = "altivec", salsa20_altivec),
+    feature(stdarch_powerpc)
+)]
+#![cfg_attr(
+    all(target_arch = "loongarch64", target_feature = "lsx", salsa20_loongarch),
+    feature(stdarch_loongarch)
+)]
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/RustCrypto/media/8f1a9894/logo.svg",
     html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/media/8f1a9894/logo.svg"
 )]
+#![allow(unexpected_cfgs)]
 #![warn(missing_docs, rust_2018_idioms, trivial_casts, unused_qualifications)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use cfg_if::cfg_if;
 pub use cipher;
 
 use cipher::{
     array::{typenum::Unsigned, Array, ArraySize},
-    consts::{U10, U24, U32, U4, U6, U64, U8},
-    Block, BlockSizeUser, IvSizeUser, KeyIvInit, KeySizeUser, StreamCipherClosure,
-    StreamCipherCore, StreamCipherCoreWrapper, StreamCipherSeekCore,
+    consts::{U10, U24, U32, U64, U8},
+    Block, BlockSizeUser, InOutBuf, InvalidLength, IvSizeUser, KeyIvInit, KeySizeUser,
+    StreamCipherClosure, StreamCipherCore, StreamCipherCoreWrapper, StreamCipherError,
+    StreamCipherSeekCore,
 };
-use core::marker::PhantomData;
 
+use core::{fmt, marker::PhantomData, mem::MaybeUninit};
+
+#[cfg(feature = "reduced-round")]
+use cipher::consts::{U4, U6};
 #[cfg(feature = "zeroize")]
 use cipher::zeroize::{Zeroize, ZeroizeOnDrop};
 
 mod backends;
+mod builder;
+mod counter;
+mod dynamic;
+#[cfg(feature = "std")]
+mod framing;
+#[cfg(fuzzing)]
+mod fuzzing;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "zeroize")]
+mod manually_zeroize;
+mod nonce128;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(all(feature = "paranoid", debug_assertions))]
+mod paranoid;
+mod partial;
+#[cfg(feature = "rand_core")]
+mod rng;
+#[cfg(feature = "zeroize")]
+mod secret;
+mod session;
+#[cfg(feature = "serde")]
+mod state;
+#[cfg(feature = "test-vectors")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-vectors")))]
+pub mod test_vectors;
+mod vectored;
 mod xsalsa;
 
-pub use xsalsa::{hsalsa, XSalsa12, XSalsa20, XSalsa8, XSalsaCore};
+pub use backends::soft::{double_round, quarter_round};
+pub use builder::SalsaCoreBuilder;
+pub use counter::CounterSize;
+#[cfg(feature = "digest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+pub use digest;
+pub use dynamic::{DynSalsa, SalsaVariant};
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use framing::{UnauthenticatedFrameReader, UnauthenticatedFrameWriter};
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use io::{SalsaReader, SalsaWriter};
+#[cfg(feature = "zeroize")]
+pub use manually_zeroize::{ManuallyZeroize, SalsaCoreRaw};
+pub use nonce128::{Nonce128, Salsa20Nonce128, SalsaNonce128Core};
+#[cfg(feature = "reduced-round")]
+pub use nonce128::{Salsa12Nonce128, Salsa8Nonce128};
+#[cfg(all(feature = "paranoid", debug_assertions))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "paranoid", debug_assertions))))]
+pub use paranoid::ConsumedPositions;
+pub use partial::ApplyKeystreamPartial;
+#[cfg(feature = "rand_core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rand_core")))]
+pub use rand_core;
+#[cfg(all(feature = "rand_core", feature = "reduced-round"))]
+pub use rng::{Salsa12Rng, Salsa8Rng};
+#[cfg(feature = "rand_core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rand_core")))]
+pub use rng::{Salsa20Rng, SalsaRng};
+#[cfg(feature = "zeroize")]
+pub use secret::{SecretKey, SecretNonce};
+#[cfg(feature = "reduced-round")]
+pub use session::{Salsa12Session, Salsa8Session};
+pub use session::{Salsa20Session, SalsaSession};
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub use state::SalsaState;
+pub use vectored::ApplyKeystreamVectored;
+pub use xsalsa::{
+    hsalsa, hsalsa_many, HSalsa, HSalsa20, HSalsa20Kdf, HSalsaKdf, XSalsa20, XSalsaCore,
+};
+#[cfg(feature = "reduced-round")]
+pub use xsalsa::{HSalsa12, HSalsa8, XSalsa12, XSalsa8};
 
 /// Salsa20/8 stream cipher
 /// (reduced-round variant of Salsa20 with 8 rounds, *not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
 pub type Salsa8 = StreamCipherCoreWrapper<SalsaCore<U4, U32>>;
 
 /// Salsa20/12 stream cipher
 /// (reduced-round variant of Salsa20 with 12 rounds, *not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
 pub type Salsa12 = StreamCipherCoreWrapper<SalsaCore<U6, U32>>;
 
 /// Salsa20/20 stream cipher
 /// (20 rounds; **recommended**)
 pub type Salsa20 = StreamCipherCoreWrapper<SalsaCore<U10, U32>>;
 
+/// Salsa stream cipher with a 256-bit key and `R` *double* rounds, i.e.
+/// Salsa20/N for `N = 2 * R`.
+///
+/// This is meant for experimenting with round counts other than those of
+/// [`Salsa20`] and the reduced-round aliases, and is available regardless of
+/// the `reduced-round` feature. `R` has to be at least one, which is checked
+/// at compile time. Any round count other than 20 is *not recommended*.
+///
+/// # Example
+/// ```
+/// use salsa20::Salsa;
+/// use salsa20::cipher::{consts::U8, KeyIvInit, StreamCipher};
+///
+/// // Salsa20/16
+/// let mut cipher = Salsa::<U8>::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// let mut buf = [0u8; 64];
+/// cipher.apply_keystream(&mut buf);
+/// ```
+///
+/// Zero rounds are rejected:
+/// ```compile_fail
+/// use salsa20::Salsa;
+/// use salsa20::cipher::{consts::U0, KeyIvInit};
+///
+/// let cipher = Salsa::<U0>::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// ```
+pub type Salsa<R> = StreamCipherCoreWrapper<SalsaCore<R, U32>>;
+
+/// Encrypt or decrypt `buf` in place with Salsa20/20, i.e. XOR it with the
+/// keystream for `key` and `nonce` starting at block 0.
+///
+/// This is a shortcut for the common case of encrypting a single message
+/// with [`Salsa20`]. The cipher is dropped before returning, which zeroizes
+/// its state with the `zeroize` feature.
+///
+/// # Security
+/// Never encrypt two messages with the same key and nonce.
+///
+/// # Example
+/// ```
+/// let mut buf = *b"hello world";
+/// salsa20::encrypt(&[0x42; 32].into(), &[0x24; 8].into(), &mut buf);
+/// assert_ne!(&buf, b"hello world");
+/// salsa20::encrypt(&[0x42; 32].into(), &[0x24; 8].into(), &mut buf);
+/// assert_eq!(&buf, b"hello world");
+/// ```
+#[inline]
+pub fn encrypt(key: &Key<U32>, nonce: &Nonce, buf: &mut [u8]) {
+    encrypt_with::<U10>(key, nonce, buf);
+}
+
+/// Salsa20/12 variant of [`encrypt`] (*not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+#[inline]
+pub fn encrypt12(key: &Key<U32>, nonce: &Nonce, buf: &mut [u8]) {
+    encrypt_with::<U6>(key, nonce, buf);
+}
+
+/// Salsa20/8 variant of [`encrypt`] (*not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+#[inline]
+pub fn encrypt8(key: &Key<U32>, nonce: &Nonce, buf: &mut [u8]) {
+    encrypt_with::<U4>(key, nonce, buf);
+}
+
+fn encrypt_with<R: Unsigned>(key: &Key<U32>, nonce: &Nonce, buf: &mut [u8]) {
+    use cipher::StreamCipher;
+    Salsa::<R>::new(key, nonce).apply_keystream(buf);
+}
+
 /// Salsa20/20 stream cipher with key of length N
 pub type Key<N> = Array<u8, N>;
 
@@ -115,6 +393,11 @@ const KEY_CONSTANTS_END: [u8; 7] = *b"-byte k";
 
 /// Generate the key expansion constants for a given key length.
 /// This will result in the bytes equivalent to "expand N-byte k", where N is the key length.
+///
+/// Salsa20 is only defined for 16- and 32-byte keys. For other lengths this
+/// still produces constants, which yield a non-standard cipher: lengths below
+/// 10 are written with a leading zero and lengths above 99 are clamped to 99.
+/// Use [`checked_constants`] to reject unsupported key lengths.
 pub const fn constants(key_len: usize) -> [u32; 4] {
     //The key len number, when converted to ASCII, can only take up two bytes of the constant to stay consistent with
     //a 32-byte key having "32" only take up two byte of the constant as `0x33 0x32`. This ensures we still
@@ -158,148 +441,1255 @@ pub const fn constants(key_len: usize) -> [u32; 4] {
     ]
 }
 
+/// Generate the key expansion constants like [`constants`], but only for the
+/// 16- and 32-byte keys Salsa20 is defined for.
+///
+/// Returns `None` for any other key length.
+pub const fn checked_constants(key_len: usize) -> Option<[u32; 4]> {
+    match key_len {
+        16 | 32 => Some(constants(key_len)),
+        _ => None,
+    }
+}
+
+/// Key expansion constants for 32-byte keys, i.e. `"expand 32-byte k"` as
+/// little-endian words, the same as `constants(32)`.
+pub const SIGMA: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// Key expansion constants for 16-byte keys, i.e. `"expand 16-byte k"` as
+/// little-endian words, the same as `constants(16)`.
+pub const TAU: [u32; 4] = [0x6170_7865, 0x3120_646e, 0x7962_2d36, 0x6b20_6574];
+
 /// Nonce type used by all Salsa variants.
 pub type Nonce = Array<u8, U8>;
 
 /// Nonce type used by [`XSalsa20`].
 pub type XNonce = Array<u8, U24>;
 
+/// Number of rounds of [`Salsa20`], i.e. twice the number of double rounds
+/// `R` of [`SalsaCore`].
+pub const SALSA20_ROUNDS: usize = 20;
+
+/// Size of a keystream block in bytes.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Size of the standard 256-bit key in bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// Size of the 128-bit key of `SalsaCore<R, U16>` in bytes.
+pub const KEY_SIZE_128: usize = 16;
+
+/// Size of a [`Nonce`] in bytes.
+pub const NONCE_SIZE: usize = 8;
+
+/// Size of an [`XNonce`] in bytes.
+pub const XNONCE_SIZE: usize = 24;
+
 /// Number of 32-bit words in the Salsa20 state
 const STATE_WORDS: usize = 16;
 
+/// CPU feature detection used for backend selection.
+///
+/// The detection result is cached by `cpufeatures`, so only the first query
+/// executes `cpuid`.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(any(salsa20_force_soft, salsa20_force_sse2, target_feature = "avx512f")),
+))]
+mod cpuid {
+    cpufeatures::new!(avx2_cpuid, "avx2");
+    cpufeatures::new!(sse2_cpuid, "sse2");
+
+    pub(crate) use avx2_cpuid::get as has_avx2;
+    pub(crate) use sse2_cpuid::get as has_sse2;
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    salsa20_force_sse2,
+    not(salsa20_force_soft),
+    not(target_feature = "sse2"),
+))]
+compile_error!(
+    "You must enable `sse2` target feature with `salsa20_force_sse2` configuration option"
+);
+
 /// The Salsa20 core function.
-pub struct SalsaCore<R: Unsigned, K: ArraySize> {
+///
+/// `R` is the number of *double* rounds, so e.g. `U10` gives Salsa20/20 and
+/// `U4` Salsa20/8, see [`Salsa`]. `K` is the key size, 16 or 32 bytes.
+///
+/// `C` is the size of the block counter, see [`CounterSize`]. The default
+/// [`U8`] is the 64-bit counter of Salsa20. `U4`
+/// selects a 32-bit counter for interoperability with systems which keep
+/// the upper counter word fixed. Its keystream ends after `2^32 - 1` blocks,
+/// i.e. 256 GiB minus 64 bytes, instead of carrying into the upper word:
+///
+/// ```
+/// use salsa20::SalsaCore;
+/// use salsa20::cipher::{
+///     consts::{U10, U32, U4}, KeyIvInit, StreamCipher, StreamCipherCoreWrapper,
+///     StreamCipherSeek,
+/// };
+///
+/// type Salsa20Ctr32 = StreamCipherCoreWrapper<SalsaCore<U10, U32, U4>>;
+///
+/// let mut cipher = Salsa20Ctr32::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// cipher.seek((u64::from(u32::MAX) - 1) * 64);
+/// cipher.apply_keystream(&mut [0; 64]);
+/// assert!(cipher.try_apply_keystream(&mut [0; 1]).is_err());
+/// ```
+pub struct SalsaCore<R: Unsigned, K: ArraySize, C: CounterSize = U8> {
     /// Internal state of the core function
     state: [u32; STATE_WORDS],
     /// Number of rounds to perform
     rounds: PhantomData<R>,
     /// Length of key in bytes
     key: PhantomData<K>,
+    /// Size of the block counter in bytes
+    counter: PhantomData<C>,
+    /// Block positions already used, see the `paranoid` feature
+    #[cfg(all(feature = "paranoid", debug_assertions))]
+    reuse_guard: paranoid::ReuseGuard,
 }
 
-impl<R: Unsigned, K: ArraySize> SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> SalsaCore<R, K, C> {
+    /// Number of rounds, e.g. 20 for Salsa20/20.
+    pub const ROUNDS: usize = R::USIZE * 2;
+
+    /// Key size in bytes.
+    pub const KEY_SIZE: usize = K::USIZE;
+
+    /// Number of blocks between two calls of the progress callback of
+    /// [`apply_keystream_with_progress`][Self::apply_keystream_with_progress],
+    /// i.e. 64 KiB of data.
+    pub const PROGRESS_BLOCKS: usize = 1024;
+
+    /// Evaluated by all constructors to reject zero rounds at compile time.
+    const CHECK_ROUNDS: () = assert!(R::USIZE >= 1, "Salsa needs at least one double round");
+
+    /// Get the number of rounds, e.g. 20 for Salsa20/20.
+    pub const fn rounds(&self) -> usize {
+        Self::ROUNDS
+    }
+
+    /// Get the key size in bytes.
+    pub const fn key_size(&self) -> usize {
+        Self::KEY_SIZE
+    }
+
     /// Create new Salsa core from raw state.
     ///
-    /// This method is mainly intended for the `scrypt` crate.
+    /// The state words are given in the canonical Salsa20 order on all
+    /// targets and for all variants: constants in words 0, 5, 10 and 15, the
+    /// key in words 1-4 and 11-14, the nonce in words 6 and 7 and the block
+    /// counter in words 8 and 9. The x86 SIMD backends keep the words in a
+    /// transposed layout internally, into which this method converts them.
+    ///
+    /// Up to version 0.10, Salsa20/20 cores on x86 instead took the words in
+    /// the transposed layout, while the other variants and targets took them
+    /// in canonical order. [`from_raw_state_le`][Self::from_raw_state_le]
+    /// names the word order explicitly.
+    ///
+    /// This method is mainly intended for the `scrypt` crate, which can use
+    /// [`SalsaCoreRaw`] to skip zeroizing every transient core on drop.
     /// Other users generally should not use this method.
     pub fn from_raw_state(state: [u32; STATE_WORDS]) -> Self {
+        let () = Self::CHECK_ROUNDS;
+        Self {
+            state: backends::to_layout(&state),
+            rounds: PhantomData,
+            key: PhantomData,
+            counter: PhantomData,
+            #[cfg(all(feature = "paranoid", debug_assertions))]
+            reuse_guard: paranoid::ReuseGuard::new(),
+        }
+    }
+
+    /// Create new Salsa core from raw state in canonical little-endian word
+    /// order, i.e. words decoded as in the Salsa20 specification, and
+    /// transpose it into the internal layout on x86.
+    ///
+    /// This is the same as [`from_raw_state`][Self::from_raw_state], for
+    /// callers which want to state the word order explicitly, e.g. when
+    /// porting code which transposed the words itself for version 0.10.
+    pub fn from_raw_state_le(state: [u32; STATE_WORDS]) -> Self {
+        Self::from_raw_state(state)
+    }
+
+    /// Extract the raw state, the inverse of [`from_raw_state`][Self::from_raw_state].
+    ///
+    /// The state words are returned in the canonical Salsa20 order on all
+    /// targets, with the block counter in words 8 and 9. Note that the x86
+    /// SIMD backends keep the words transposed into a diagonal layout
+    /// internally, so the order of the words in memory differs by target and
+    /// must not be relied upon; only this method and
+    /// [`from_raw_state`][Self::from_raw_state] convert between the two.
+    pub fn into_raw_state(self) -> [u32; STATE_WORDS] {
+        self.canonical_state()
+    }
+
+    /// Get the state words in the canonical Salsa20 order, see
+    /// [`into_raw_state`][Self::into_raw_state].
+    ///
+    /// The order is the same on all targets, whichever layout the active
+    /// backend uses internally, which makes the state comparable across
+    /// targets when debugging.
+    ///
+    /// # Security
+    /// The state contains the key. Do not log it outside of tests.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let words = core.state_words();
+    /// assert_eq!(words[0], u32::from_le_bytes(*b"expa"));
+    /// assert_eq!(words[6], 0x2424_2424);
+    /// ```
+    pub fn state_words(&self) -> [u32; STATE_WORDS] {
+        self.canonical_state()
+    }
+
+    /// Create new Salsa core with custom constants `sigma` in place of the
+    /// "expand N-byte k" constants, i.e. state words 0, 5, 10 and 15.
+    ///
+    /// **This is a non-standard hazmat extension.** The result is *not*
+    /// Salsa20 unless `sigma` equals [`constants`] of the key size, and its
+    /// security has not been analyzed. It is meant for building
+    /// personalized Salsa-based primitives, e.g. a KDF using a
+    /// domain-separation string, similar to the BLAKE2 personalization.
+    ///
+    /// ```
+    /// use salsa20::{constants, SalsaCore};
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let (key, nonce) = ([0x42; 32].into(), [0x24; 8].into());
+    /// let custom = SalsaCore::<U10, U32>::new_with_constants(&key, &nonce, constants(32));
+    /// assert_eq!(custom, SalsaCore::new(&key, &nonce));
+    /// ```
+    pub fn new_with_constants(key: &Key<K>, iv: &Nonce, sigma: [u32; 4]) -> Self {
+        let mut state = Self::new(key, iv).canonical_state();
+        state[0] = sigma[0];
+        state[5] = sigma[1];
+        state[10] = sigma[2];
+        state[15] = sigma[3];
+        let core = Self::from_raw_state(state);
+
+        #[cfg(feature = "zeroize")]
+        state.zeroize();
+
+        core
+    }
+
+    /// Re-initialize the core in place with a new key and nonce, e.g. to
+    /// reuse a pooled cipher object for another session.
+    ///
+    /// The result is the same as a core created with [`KeyIvInit::new`]. With
+    /// the `zeroize` feature the old state is zeroized first.
+    pub fn rekey(&mut self, key: &Key<K>, nonce: &Nonce) {
+        #[cfg(feature = "zeroize")]
+        self.state.zeroize();
+        self.state = Self::from_key_iv(key, &nonce.0).state;
+        self.reset_reuse_guard();
+    }
+
+    /// Create new Salsa core from a key and a nonce given as slices, e.g.
+    /// read from a file or received over the network.
+    ///
+    /// Returns [`InvalidLength`] instead of panicking if `key` is not exactly
+    /// `K::USIZE` bytes long or `nonce` is not exactly 8 bytes long, and for
+    /// key sizes other than 16 and 32 bytes, see [`checked_constants`].
+    /// The lengths are checked before any bytes are read, so this never
+    /// panics, e.g. for empty or truncated slices parsed from untrusted
+    /// headers.
+    ///
+    /// # Example
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::consts::{U10, U32};
+    ///
+    /// assert!(SalsaCore::<U10, U32>::try_new(&[0x42; 32], &[0x24; 8]).is_ok());
+    /// assert!(SalsaCore::<U10, U32>::try_new(&[0x42; 16], &[0x24; 8]).is_err());
+    /// ```
+    pub fn try_new(key: &[u8], nonce: &[u8]) -> Result<Self, InvalidLength> {
+        if key.len() != K::USIZE || checked_constants(K::USIZE).is_none() {
+            return Err(InvalidLength);
+        }
+        let nonce: &[u8; 8] = nonce.try_into().map_err(|_| InvalidLength)?;
+        Ok(Self::from_key_iv(key, nonce))
+    }
+
+    /// Get the index of the next 64-byte keystream block.
+    ///
+    /// Same as [`StreamCipherSeekCore::get_block_pos`], converted to `u64`.
+    #[inline]
+    pub fn current_block(&self) -> u64 {
+        C::to_pos(self.get_block_pos())
+    }
+
+    /// Get the number of keystream blocks left before the block counter
+    /// would wrap around.
+    ///
+    /// Unlike [`StreamCipherCore::remaining_blocks`] the count is exact on
+    /// all targets, as it is not converted to `usize`. The block at position
+    /// [`C::MAX_POS`][CounterSize::MAX_POS] is never generated, so a new core
+    /// has `u64::MAX` blocks with the 64-bit counter.
+    ///
+    /// Ciphers wrapped in a [`StreamCipherCoreWrapper`] can be queried with
+    /// `cipher.get_core().remaining_blocks()`. Note that the wrapper may have
+    /// already generated and buffered the keystream of the block at the
+    /// current position.
+    #[inline]
+    pub fn remaining_blocks(&self) -> u64 {
+        C::MAX_POS - self.current_block()
+    }
+
+    /// Get the number of keystream bytes left before the block counter
+    /// would wrap around, i.e. 64 times
+    /// [`remaining_blocks`][Self::remaining_blocks].
+    ///
+    /// Returns `None` if the number does not fit into `u64`, which is the
+    /// case for all block positions below `2^64 - 2^58`. Clients which have
+    /// to re-key before exhausting the keystream can treat `None` as
+    /// "plenty left".
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// assert_eq!(core.remaining_bytes(), None);
+    /// core.seek_to_block(u64::MAX - 2);
+    /// assert_eq!(core.remaining_bytes(), Some(128));
+    /// ```
+    #[inline]
+    pub fn remaining_bytes(&self) -> Option<u64> {
+        self.remaining_blocks().checked_mul(64)
+    }
+
+    /// Seek to the keystream block `pos` like
+    /// [`StreamCipherSeekCore::set_block_pos`], but fail early if no block
+    /// would be left to generate at `pos`.
+    ///
+    /// The only such position is [`C::MAX_POS`][CounterSize::MAX_POS], whose
+    /// block is never generated, see [`remaining_blocks`][Self::remaining_blocks].
+    /// On error the block position is left unchanged.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// assert!(core.try_set_block_pos(u64::MAX - 1).is_ok());
+    /// assert!(core.try_set_block_pos(u64::MAX).is_err());
+    /// assert_eq!(core.remaining_blocks(), 1);
+    /// ```
+    #[inline]
+    pub fn try_set_block_pos(&mut self, pos: C::Counter) -> Result<(), StreamCipherError> {
+        if C::to_pos(pos) >= C::MAX_POS {
+            return Err(StreamCipherError);
+        }
+        self.set_block_pos(pos);
+        Ok(())
+    }
+
+    /// Zeroize the state, e.g. before re-keying in place, without waiting
+    /// for the core to be dropped.
+    ///
+    /// The core can still be used afterwards: its block position is 0 and it
+    /// generates the keystream of an all-zero state, which consists of zeros.
+    #[cfg(feature = "zeroize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+    #[inline]
+    pub fn zeroize(&mut self) {
+        self.state.zeroize();
+        self.reset_reuse_guard();
+    }
+
+    /// Get the audit of the keystream block positions generated since the
+    /// core was created, re-keyed or zeroized.
+    ///
+    /// Only available in debug builds with the `paranoid` feature, so that
+    /// the audit costs nothing otherwise.
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "paranoid", debug_assertions))] {
+    /// use salsa20::{ConsumedPositions, SalsaCore};
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit, StreamCipherSeekCore};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// core.apply_keystream_blocks(&mut [0; 128]).unwrap();
+    /// core.set_block_pos(10);
+    /// core.apply_keystream_blocks(&mut [0; 64]).unwrap();
+    ///
+    /// let consumed = core.consumed_positions();
+    /// assert_eq!(consumed.blocks(), 3);
+    /// assert_eq!(consumed.digest(), ConsumedPositions::digest_of([0, 1, 10]));
+    /// # }
+    /// ```
+    #[cfg(all(feature = "paranoid", debug_assertions))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "paranoid", debug_assertions))))]
+    pub fn consumed_positions(&self) -> ConsumedPositions {
+        self.reuse_guard.consumed()
+    }
+
+    /// Forget which blocks were generated, see the `paranoid` feature.
+    #[inline(always)]
+    pub(crate) fn reset_reuse_guard(&mut self) {
+        #[cfg(all(feature = "paranoid", debug_assertions))]
+        {
+            self.reuse_guard = paranoid::ReuseGuard::new();
+        }
+    }
+
+    /// Get the state words in the canonical Salsa20 order.
+    fn canonical_state(&self) -> [u32; STATE_WORDS] {
+        backends::from_layout(&self.state)
+    }
+
+    /// Create a temporary core at the same block position, e.g. to generate
+    /// keystream through a shared reference. The copy of the state is
+    /// zeroized when the core is dropped.
+    fn fork(&self) -> Self {
+        #[allow(unused_mut)]
+        let mut state = self.canonical_state();
+        let core = Self::from_raw_state(state);
+
+        #[cfg(feature = "zeroize")]
+        state.zeroize();
+
+        core
+    }
+
+    /// Compute the keystream block at the current block position and advance
+    /// the block counter by one.
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    #[must_use = "use `set_block_pos` to skip keystream blocks"]
+    pub fn block(&mut self) -> Block<Self> {
+        let mut block = Block::<Self>::default();
+        self.gen_ks_block(&mut block);
+        block
+    }
+
+    /// Write the keystream block at the current block position into `block`
+    /// and advance the block counter by one.
+    ///
+    /// Same as [`block`][Self::block], but writes into a buffer owned by the
+    /// caller. The block is generated by the active backend, so callers
+    /// implementing their own combiners need not go through
+    /// [`StreamCipherCore::process_with_backend`].
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut block = Default::default();
+    /// core.gen_ks_block(&mut block);
+    /// assert_eq!(block[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// assert_eq!(core.current_block(), 1);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    #[inline]
+    pub fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        self.write_keystream_block(block);
+    }
+
+    /// XOR the keystream block at the current block position into `block`
+    /// and advance the block counter by one.
+    ///
+    /// This is [`StreamCipherCore::apply_keystream_block_inout`] for callers
+    /// which manage 64-byte blocks themselves. The counter advances by one
+    /// block per call, so consecutive calls process consecutive blocks.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut block = [0u8; 64].into();
+    /// core.xor_block(&mut block);
+    /// assert_eq!(block[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// assert_eq!(core.current_block(), 1);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    #[inline]
+    pub fn xor_block(&mut self, block: &mut Block<Self>) {
+        self.apply_keystream_block_inout(block.into());
+    }
+
+    /// Compute the keystream block at position `block` without changing the
+    /// block position of the core.
+    ///
+    /// The result is the same as that of [`block`][Self::block] after
+    /// seeking to `block`, which makes random access possible through a
+    /// shared reference.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let block = core.keystream_at(0);
+    /// assert_eq!(block[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// assert_eq!(core.current_block(), 0);
+    /// ```
+    pub fn keystream_at(&self, block: C::Counter) -> Block<Self> {
+        let mut core = self.fork();
+        core.set_block_pos(block);
+        core.block()
+    }
+
+    /// Iterate over successive keystream blocks, starting at the current
+    /// block position.
+    ///
+    /// Each call to `next` advances the block counter by one. The iterator
+    /// stops before the block counter would wrap around.
+    pub fn keystream_blocks(&mut self) -> impl Iterator<Item = Block<Self>> + '_ {
+        core::iter::from_fn(move || match self.remaining_blocks() {
+            0 => None,
+            _ => Some(self.block()),
+        })
+    }
+
+    /// Iterate over successive keystream bytes, starting at the current
+    /// block position.
+    ///
+    /// The keystream is generated one block at a time, see
+    /// [`keystream_blocks`][Self::keystream_blocks]: the block counter
+    /// advances when the first byte of a block is yielded, so the unused
+    /// bytes of the last block are discarded when the iterator is dropped.
+    /// The iterator stops before the block counter would wrap around.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let plaintext = [0x00, 0x01, 0x02, 0x03];
+    /// let ciphertext: Vec<u8> = plaintext
+    ///     .iter()
+    ///     .zip(core.keystream_bytes())
+    ///     .map(|(p, k)| p ^ k)
+    ///     .collect();
+    /// assert_eq!(ciphertext, [0x85, 0x84, 0x3c, 0xc5]);
+    /// ```
+    pub fn keystream_bytes(&mut self) -> impl Iterator<Item = u8> + '_ {
+        self.keystream_blocks().flatten()
+    }
+
+    /// Write raw keystream bytes into `buf`, starting at the current block,
+    /// and return the number of bytes written, i.e. `buf.len()`.
+    ///
+    /// The block counter is advanced by the number of blocks touched, so the
+    /// unused part of a trailing partial block is discarded.
+    ///
+    /// Like [`block`][Self::block] this is `#[must_use]`, so calls whose
+    /// result is dropped, e.g. because they were meant to skip keystream,
+    /// are caught by the `unused_must_use` lint:
+    ///
+    /// ```compile_fail
+    /// #![deny(unused_must_use)]
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// core.generate_keystream(&mut [0u8; 100]);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    #[must_use = "the keystream is written to `buf`, use `set_block_pos` to skip keystream"]
+    pub fn generate_keystream(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len();
+        let (blocks, tail) = Block::<Self>::slice_as_chunks_mut(buf);
+        self.write_keystream_blocks(blocks);
+
+        if !tail.is_empty() {
+            let block = self.block();
+            tail.copy_from_slice(&block[..tail.len()]);
+        }
+        n
+    }
+
+    /// Write raw keystream bytes into the possibly uninitialized `buf`,
+    /// starting at the current block, and return it as initialized slice.
+    ///
+    /// This saves zeroing a large buffer which is overwritten right away,
+    /// e.g. when filling freshly allocated memory with random bytes. The
+    /// keystream is generated into a small stack buffer and copied, so the
+    /// result is the same as with [`generate_keystream`][Self::generate_keystream].
+    ///
+    /// ```
+    /// use core::mem::MaybeUninit;
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut buf = [MaybeUninit::uninit(); 4];
+    /// assert_eq!(core.generate_keystream_uninit(&mut buf), [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    pub fn generate_keystream_uninit<'a>(
+        &mut self,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> &'a mut [u8] {
+        let mut tmp = [0u8; 8 * 64];
+        for chunk in buf.chunks_mut(tmp.len()) {
+            let n = self.generate_keystream(&mut tmp[..chunk.len()]);
+            for (dst, src) in chunk.iter_mut().zip(tmp[..n].iter()) {
+                dst.write(*src);
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        tmp.zeroize();
+
+        // SAFETY: every byte of `buf` was written above, and `MaybeUninit<u8>`
+        // has the same layout as `u8`
+        unsafe { core::slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<u8>(), buf.len()) }
+    }
+
+    /// Apply keystream to `buf`, starting at the current block, using a
+    /// fixed-size stack buffer of `N` bytes for the keystream.
+    ///
+    /// The keystream is generated into the scratch buffer `N` bytes at a
+    /// time and XORed into `buf`, so the memory used is known at compile
+    /// time and nothing is allocated, which suits targets without a heap.
+    /// `N` has to be a non-zero multiple of the 64-byte block size, which is
+    /// checked at compile time. A multiple of the batch size of the backend,
+    /// e.g. 256 or 512, lets it generate full batches; larger values only
+    /// cost stack space. The block counter is advanced like with
+    /// [`StreamCipher::apply_keystream`][cipher::StreamCipher::apply_keystream],
+    /// so the unused part of a trailing partial block is discarded.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut buf = [0u8; 1000];
+    /// core.encrypt_chunks::<256>(&mut buf);
+    /// assert_eq!(buf[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// # Panics
+    /// If processing `buf` would overflow the 64-bit block counter. `buf` is
+    /// left untouched in that case.
+    pub fn encrypt_chunks<const N: usize>(&mut self, buf: &mut [u8]) {
+        const {
+            assert!(
+                N > 0 && N % 64 == 0,
+                "scratch size must be a multiple of 64"
+            )
+        };
+        let blocks = buf.len().div_ceil(64) as u64;
+        assert!(blocks <= self.remaining_blocks(), "block counter overflow");
+
+        let mut scratch = [0u8; N];
+        for chunk in buf.chunks_mut(N) {
+            let n = self.generate_keystream(&mut scratch[..chunk.len()]);
+            for (b, k) in chunk.iter_mut().zip(scratch[..n].iter()) {
+                *b ^= k;
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        scratch.zeroize();
+    }
+
+    /// Apply keystream to `buf` like
+    /// [`StreamCipher::apply_keystream`][cipher::StreamCipher::apply_keystream],
+    /// calling `on_block` with the block position reached after every
+    /// [`PROGRESS_BLOCKS`][Self::PROGRESS_BLOCKS] blocks and once at the end.
+    ///
+    /// This lets applications encrypting large buffers report progress
+    /// without splitting the buffer themselves. The last reported position
+    /// equals [`current_block`][Self::current_block] after the call, and an
+    /// empty `buf` reports nothing. As with
+    /// [`keystream_xor_inout`][Self::keystream_xor_inout] the unused part of
+    /// a trailing partial block is discarded.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut buf = vec![0u8; 200_000];
+    /// let mut reported = Vec::new();
+    /// core.apply_keystream_with_progress(&mut buf, |pos| reported.push(pos));
+    /// assert_eq!(reported, [1024, 2048, 3072, 3125]);
+    /// ```
+    ///
+    /// # Panics
+    /// If processing `buf` would overflow the block counter. `buf` is left
+    /// untouched and `on_block` is not called in that case.
+    pub fn apply_keystream_with_progress(&mut self, buf: &mut [u8], mut on_block: impl FnMut(u64)) {
+        let blocks = buf.len().div_ceil(64) as u64;
+        assert!(blocks <= self.remaining_blocks(), "block counter overflow");
+
+        for chunk in buf.chunks_mut(64 * Self::PROGRESS_BLOCKS) {
+            let res = self.keystream_xor_inout(chunk.into());
+            debug_assert!(res.is_ok());
+            on_block(self.current_block());
+        }
+    }
+
+    /// Write `n` keystream bytes, starting at the current block, to `out` as
+    /// lowercase hex.
+    ///
+    /// This is meant for dumping keystream to compare it with other
+    /// implementations, e.g. when generating test fixtures, and needs no
+    /// allocation. Like with [`generate_keystream`][Self::generate_keystream],
+    /// the unused part of a trailing partial block is discarded.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut hex = String::new();
+    /// core.write_keystream_hex(&mut hex, 4).unwrap();
+    /// assert_eq!(hex, "85853ec6");
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    pub fn write_keystream_hex<W: fmt::Write>(&mut self, out: &mut W, n: usize) -> fmt::Result {
+        let mut buf = Block::<Self>::default();
+        let mut left = n;
+        while left > 0 {
+            let ks = &mut buf[..left.min(64)];
+            left -= self.generate_keystream(ks);
+            for b in ks.iter() {
+                write!(out, "{b:02x}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass `n` keystream bytes, starting at the current block, to `update`
+    /// in chunks of at most 256 bytes.
+    ///
+    /// This feeds keystream into a hash function or any other sink without
+    /// an intermediate buffer for all of it. With the `digest` feature,
+    /// `write_keystream_to_digest` does so for any `digest::Update` hasher. The
+    /// chunks are generated in batches of blocks, so the bulk of the data
+    /// takes the parallel path of the backend. Like with
+    /// [`generate_keystream`][Self::generate_keystream], the unused part of a
+    /// trailing partial block is discarded.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut keystream = Vec::new();
+    /// core.write_keystream_to(|chunk| keystream.extend_from_slice(chunk), 1000);
+    /// assert_eq!(keystream.len(), 1000);
+    /// assert_eq!(keystream[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    pub fn write_keystream_to(&mut self, mut update: impl FnMut(&[u8]), n: usize) {
+        let mut scratch = [0u8; 4 * 64];
+        let mut left = n;
+        while left > 0 {
+            let ks = &mut scratch[..left.min(4 * 64)];
+            left -= self.generate_keystream(ks);
+            update(ks);
+        }
+
+        #[cfg(feature = "zeroize")]
+        scratch.zeroize();
+    }
+
+    /// Feed `n` keystream bytes, starting at the current block, into
+    /// `hasher`, see [`write_keystream_to`][Self::write_keystream_to].
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    /// use sha2::{Digest, Sha256};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut hasher = Sha256::new();
+    /// core.write_keystream_to_digest(&mut hasher, 1000);
+    /// let hash = hasher.finalize();
+    /// # assert_eq!(hash.len(), 32);
+    /// ```
+    ///
+    /// WARNING: this method does not check number of remaining blocks!
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    pub fn write_keystream_to_digest(&mut self, hasher: &mut impl digest::Update, n: usize) {
+        self.write_keystream_to(|chunk| hasher.update(chunk), n);
+    }
+
+    /// Apply keystream to the input buffer of `buf`, writing the result into
+    /// its output buffer, starting at the current block.
+    ///
+    /// Unlike [`StreamCipher::apply_keystream`][cipher::StreamCipher::apply_keystream]
+    /// this leaves the input data untouched. The block counter is advanced by
+    /// the number of blocks touched, so the unused part of a trailing partial
+    /// block is discarded.
+    ///
+    /// Returns an error if processing `buf` would overflow the 64-bit block
+    /// counter, in which case the output and the block position are left
+    /// untouched.
+    pub fn keystream_xor_inout(
+        &mut self,
+        buf: InOutBuf<'_, '_, u8>,
+    ) -> Result<(), StreamCipherError> {
+        let (blocks, mut tail) = buf.into_chunks::<U64>();
+        if (blocks.len() + usize::from(!tail.is_empty())) as u64 > self.remaining_blocks() {
+            return Err(StreamCipherError);
+        }
+
+        self.apply_keystream_blocks_inout(blocks);
+        if !tail.is_empty() {
+            let block = self.block();
+            let n = tail.len();
+            tail.xor_in2out(&block[..n]);
+        }
+        Ok(())
+    }
+
+    /// Number of blocks the active backend generates per parallel batch
+    /// (e.g. 8 for AVX-512, 4 for AVX2, NEON, simd128 and software, 1 for SSE2).
+    ///
+    /// The RVV backend reports 16 blocks: it splits each batch into as many
+    /// strips as the vector length of the CPU requires.
+    pub fn par_blocks(&self) -> usize {
+        cfg_if! {
+            if #[cfg(salsa20_force_soft)] {
+                4
+            } else if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), salsa20_force_sse2))] {
+                1
+            } else if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx512f"))] {
+                8
+            } else if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+                if cpuid::has_sse2() && !cpuid::has_avx2() {
+                    1
+                } else {
+                    4
+                }
+            } else if #[cfg(all(target_arch = "riscv64", target_feature = "v"))] {
+                16
+            } else {
+                // NEON, AltiVec, LSX, simd128 and the software backend
+                4
+            }
+        }
+    }
+
+    /// Apply keystream to `buf`, whose length must be a multiple of the
+    /// 64-byte block size.
+    ///
+    /// The whole buffer is processed by a single backend invocation in
+    /// batches of [`par_blocks`][Self::par_blocks] blocks, which avoids the
+    /// per-call overhead of [`StreamCipher::apply_keystream`][cipher::StreamCipher::apply_keystream]
+    /// for bulk data.
+    ///
+    /// Returns an error if `buf.len()` is not a multiple of the block size or
+    /// if processing it would overflow the 64-bit block counter, in which
+    /// case `buf` and the block position are left untouched.
+    pub fn apply_keystream_blocks(&mut self, buf: &mut [u8]) -> Result<(), StreamCipherError> {
+        let (blocks, tail) = Block::<Self>::slice_as_chunks_mut(buf);
+        if !tail.is_empty() {
+            return Err(StreamCipherError);
+        }
+        if blocks.len() as u64 > self.remaining_blocks() {
+            return Err(StreamCipherError);
+        }
+        StreamCipherCore::apply_keystream_blocks(self, blocks);
+        Ok(())
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> SalsaCore<R, K> {
+    /// Create new Salsa core starting at block position `counter`.
+    ///
+    /// Equivalent to [`KeyIvInit::new`] followed by
+    /// [`StreamCipherSeekCore::set_block_pos`], e.g. to start decrypting a
+    /// disk sector at a known offset.
+    pub fn new_with_counter(key: &Key<K>, iv: &Nonce, counter: u64) -> Self {
+        let mut core = Self::new(key, iv);
+        core.set_block_pos(counter);
+        core
+    }
+
+    /// Create new [`SalsaCoreBuilder`] for a core with the given key and
+    /// nonce given as slices.
+    pub fn builder<'a>(key: &'a [u8], nonce: &'a [u8]) -> SalsaCoreBuilder<'a, R, K> {
+        SalsaCoreBuilder::new(key, nonce)
+    }
+
+    /// Seek to the start of the 64-byte keystream block `block`.
+    ///
+    /// Same as [`StreamCipherSeekCore::set_block_pos`], without the need to
+    /// convert a block number into a byte position.
+    #[inline]
+    pub fn seek_to_block(&mut self, block: u64) {
+        self.set_block_pos(block);
+    }
+
+    /// Get the logical parameters of the core: the eight key words, the two
+    /// nonce words and the block position.
+    ///
+    /// The words are little-endian decodings of the key and nonce bytes in
+    /// the order of the Salsa20 specification, on all targets. Together with
+    /// the block position they are enough to rebuild an identical core with
+    /// [`from_snapshot`][Self::from_snapshot], e.g. to reproduce a simulation
+    /// on another machine. For 16-byte keys the last four key words repeat
+    /// the first four. Custom constants as used by
+    /// [`new_with_constants`][Self::new_with_constants] are not part of the
+    /// snapshot, see [`into_raw_state`][Self::into_raw_state] for the full
+    /// state.
+    ///
+    /// The snapshot contains the key! It must be stored with the same care
+    /// as the key itself.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit, StreamCipherSeekCore};
+    ///
+    /// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// core.set_block_pos(1234);
+    ///
+    /// let (key, nonce, counter) = core.snapshot();
+    /// assert_eq!(key, [0x4242_4242; 8]);
+    /// assert_eq!(nonce, [0x2424_2424; 2]);
+    /// assert_eq!(counter, 1234);
+    /// assert_eq!(SalsaCore::<U10, U32>::from_snapshot(key, nonce, counter), core);
+    /// ```
+    pub fn snapshot(&self) -> ([u32; 8], [u32; 2], u64) {
+        let s = self.canonical_state();
+        let key = [s[1], s[2], s[3], s[4], s[11], s[12], s[13], s[14]];
+        (key, [s[6], s[7]], self.current_block())
+    }
+
+    /// Rebuild a core from the parameters returned by
+    /// [`snapshot`][Self::snapshot], using the standard constants for the
+    /// key size.
+    pub fn from_snapshot(key: [u32; 8], nonce: [u32; 2], counter: u64) -> Self {
+        let [c0, c1, c2, c3] = constants(K::USIZE);
+        let [k0, k1, k2, k3, k4, k5, k6, k7] = key;
+        #[rustfmt::skip]
+        let state = [
+            c0, k0, k1, k2,
+            k3, c1, nonce[0], nonce[1],
+            counter as u32, (counter >> 32) as u32, c2, k4,
+            k5, k6, k7, c3,
+        ];
+        Self::from_raw_state(state)
+    }
+
+    /// Apply keystream to `buf`, which holds the data at byte `offset` of
+    /// the stream, e.g. a memory-mapped region of a file at that offset.
+    ///
+    /// The keystream starts at `offset` regardless of the current block
+    /// position, and is applied to all full blocks at once by the active
+    /// backend, so encrypting a file region by region gives the same result
+    /// as encrypting the whole file in one go. Like
+    /// [`keystream_at`][Self::keystream_at] this does not change the core,
+    /// which makes it possible to process several regions with the same core.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut file = [0u8; 300];
+    /// let (head, tail) = file.split_at_mut(100);
+    /// core.apply_keystream_at(100, tail);
+    /// core.apply_keystream_at(0, head);
+    /// assert_eq!(file[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// This method is not gated behind the `std` feature or a memory-mapping
+    /// feature, as it works on any `&mut [u8]`: mapping the file is up to
+    /// the caller, with the crate of their choice, and the slice needs no
+    /// particular alignment.
+    ///
+    /// # Panics
+    /// If `offset + buf.len()` overflows `u64`.
+    pub fn apply_keystream_at(&self, offset: u64, buf: &mut [u8]) {
+        let end = offset.checked_add(buf.len() as u64);
+        assert!(end.is_some(), "keystream position overflow");
+
+        let mut core = self.fork();
+        core.set_block_pos(offset / 64);
+
+        // A region starting within a block is aligned with the next block,
+        // so the bulk of the data is processed in full blocks
+        let skip = (offset % 64) as usize;
+        let head_len = if skip == 0 {
+            0
+        } else {
+            (64 - skip).min(buf.len())
+        };
+        let (head, tail) = buf.split_at_mut(head_len);
+        if !head.is_empty() {
+            #[allow(unused_mut)]
+            let mut block = core.block();
+            for (b, k) in head.iter_mut().zip(&block[skip..]) {
+                *b ^= k;
+            }
+
+            #[cfg(feature = "zeroize")]
+            block.zeroize();
+        }
+        // The block counter covers all byte offsets representable by `u64`
+        let res = core.keystream_xor_inout(tail.into());
+        debug_assert!(res.is_ok());
+    }
+}
+
+/// Cloning forks the cipher at its current block position: the clone
+/// continues with exactly the same keystream as the original.
+///
+/// Note that the clone is an independent copy of the key-derived state.
+/// With the `zeroize` feature each copy is zeroized when it is dropped,
+/// so every clone has to be dropped to erase the key material.
+impl<R: Unsigned, K: ArraySize, C: CounterSize> Clone for SalsaCore<R, K, C> {
+    #[inline]
+    fn clone(&self) -> Self {
         Self {
-            state,
+            state: self.state,
             rounds: PhantomData,
             key: PhantomData,
+            counter: PhantomData,
+            #[cfg(all(feature = "paranoid", debug_assertions))]
+            reuse_guard: self.reuse_guard.clone(),
         }
     }
 }
 
-impl<R: Unsigned, K: ArraySize> KeySizeUser for SalsaCore<R, K> {
+/// Equivalent to [`SalsaCore::from_raw_state`].
+impl<R: Unsigned, K: ArraySize, C: CounterSize> From<[u32; STATE_WORDS]> for SalsaCore<R, K, C> {
+    #[inline]
+    fn from(state: [u32; STATE_WORDS]) -> Self {
+        Self::from_raw_state(state)
+    }
+}
+
+/// The key-derived state is never printed.
+impl<R: Unsigned, K: ArraySize, C: CounterSize> fmt::Debug for SalsaCore<R, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SalsaCore")
+            .field("double_rounds", &R::USIZE)
+            .field("key_size", &K::USIZE)
+            .field("block_pos", &self.current_block())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Two cores are equal if their full states, i.e. key, nonce and block
+/// position, are equal.
+///
+/// The comparison is **not** constant-time. It is intended for tests and
+/// tooling, and must not be used to compare secret state, e.g. to check
+/// whether a core was created from a given key.
+impl<R: Unsigned, K: ArraySize, C: CounterSize> PartialEq for SalsaCore<R, K, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+}
+
+impl<R: Unsigned, K: ArraySize, C: CounterSize> Eq for SalsaCore<R, K, C> {}
+
+/// Hashes the full state consistently with [`PartialEq`], so cores can be
+/// used as keys of a `HashMap`, e.g. to memoize results in tooling.
+///
+/// Like the comparison, hashing is **not** constant-time, and the hash of a
+/// core reveals information about its key to anyone who can observe it.
+/// Therefore this impl is only available with the `hash` feature.
+#[cfg(feature = "hash")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hash")))]
+impl<R: Unsigned, K: ArraySize, C: CounterSize> core::hash::Hash for SalsaCore<R, K, C> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.state.hash(state);
+    }
+}
+
+impl<R: Unsigned, K: ArraySize, C: CounterSize> KeySizeUser for SalsaCore<R, K, C> {
     type KeySize = K;
 }
 
-impl<R: Unsigned, K: ArraySize> IvSizeUser for SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> IvSizeUser for SalsaCore<R, K, C> {
     type IvSize = U8;
 }
 
-impl<R: Unsigned, K: ArraySize> BlockSizeUser for SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> BlockSizeUser for SalsaCore<R, K, C> {
     type BlockSize = U64;
 }
 
-impl<R: Unsigned, K: ArraySize> KeyIvInit for SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> KeyIvInit for SalsaCore<R, K, C> {
     fn new(key: &Key<K>, iv: &Nonce) -> Self {
-        let mut state = [0u32; STATE_WORDS];
-        let constants = constants(key.len());
-        state[0] = constants[0];
-
-        for (i, chunk) in key[..key.len().min(16)].chunks(4).enumerate() {
-            state[1 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
-        }
+        Self::from_key_iv(key, &iv.0)
+    }
+}
 
-        state[5] = constants[1];
+impl<R: Unsigned, K: ArraySize, C: CounterSize> SalsaCore<R, K, C> {
+    /// Create new Salsa core in const context, e.g. to initialize a `static`.
+    ///
+    /// Produces the same core as [`KeyIvInit::new`].
+    ///
+    /// # Panics
+    /// If `N` is not equal to the key size `K`. In const context this is
+    /// reported as a compile-time error.
+    pub const fn new_const<const N: usize>(key: &[u8; N], iv: &[u8; 8]) -> Self {
+        assert!(N == K::USIZE, "key length must be equal to the key size");
+        Self::from_key_iv(key, iv)
+    }
 
-        for (i, chunk) in iv.chunks(4).enumerate() {
-            state[6 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
+    /// Build the core from the key and IV bytes in the state layout used by
+    /// the active backend.
+    const fn from_key_iv(key: &[u8], iv: &[u8; 8]) -> Self {
+        let () = Self::CHECK_ROUNDS;
+        const fn word(bytes: &[u8], i: usize) -> u32 {
+            u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
         }
 
-        state[8] = 0;
-        state[9] = 0;
-        state[10] = constants[2];
-
-        for (i, chunk) in key[key.len().saturating_sub(16)..].chunks(4).enumerate() {
-            state[11 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
-        }
+        // A 128-bit key is used for both key slots
+        let hi = key.len().saturating_sub(16);
+        let constants = constants(key.len());
 
+        let mut state = [0u32; STATE_WORDS];
+        state[0] = constants[0];
+        state[5] = constants[1];
+        state[10] = constants[2];
         state[15] = constants[3];
+        state[6] = word(iv, 0);
+        state[7] = word(iv, 4);
 
-        cfg_if! {
-            if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
-                state = [
-                    state[0], state[5], state[10], state[15],
-                    state[4], state[9], state[14], state[3],
-                    state[8], state[13], state[2], state[7],
-                    state[12], state[1], state[6], state[11],
-                ];
-            }
+        let mut i = 0;
+        while i < 4 {
+            state[1 + i] = word(key, 4 * i);
+            state[11 + i] = word(key, hi + 4 * i);
+            i += 1;
         }
 
         Self {
-            state,
+            state: backends::to_layout(&state),
             rounds: PhantomData,
             key: PhantomData,
+            counter: PhantomData,
+            #[cfg(all(feature = "paranoid", debug_assertions))]
+            reuse_guard: paranoid::ReuseGuard::new(),
         }
     }
 }
 
-impl<R: Unsigned, K: ArraySize> StreamCipherCore for SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> StreamCipherCore for SalsaCore<R, K, C> {
     #[inline(always)]
     fn remaining_blocks(&self) -> Option<usize> {
-        let rem = u64::MAX - self.get_block_pos();
-        rem.try_into().ok()
+        // The block at position `C::MAX_POS` is never used, as the counter
+        // would wrap around to zero after it and repeat the keystream. On targets
+        // with a 32-bit `usize` saturate instead of returning `None`, which
+        // would disable the remaining blocks checks altogether.
+        let rem = SalsaCore::remaining_blocks(self);
+        Some(rem.try_into().unwrap_or(usize::MAX))
     }
     fn process_with_backend(&mut self, f: impl StreamCipherClosure<BlockSize = Self::BlockSize>) {
+        #[cfg(all(feature = "paranoid", debug_assertions))]
+        let start = self.current_block();
+
         cfg_if! {
-            if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+            if #[cfg(salsa20_force_soft)] {
+                f.call(&mut backends::soft::Backend::<R>::new(&mut self.state));
+            } else if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), salsa20_force_sse2))] {
                 unsafe {
                     backends::sse2::inner::<R, K, _>(&mut self.state, f);
                 }
+            } else if #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx512f"))] {
+                unsafe {
+                    backends::avx512::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+                if cpuid::has_avx2() {
+                    unsafe {
+                        backends::avx2::inner::<R, K, _>(&mut self.state, f);
+                    }
+                } else if cpuid::has_sse2() {
+                    unsafe {
+                        backends::sse2::inner::<R, K, _>(&mut self.state, f);
+                    }
+                } else {
+                    backends::soft::inner::<R, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "aarch64", target_feature = "neon"))] {
+                unsafe {
+                    backends::neon::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "arm", target_feature = "neon", salsa20_arm_neon))] {
+                unsafe {
+                    backends::neon_arm::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "powerpc64", target_feature = "altivec", salsa20_altivec))] {
+                unsafe {
+                    backends::altivec::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "riscv64", target_feature = "v"))] {
+                unsafe {
+                    backends::rvv::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "loongarch64", target_feature = "lsx", salsa20_loongarch))] {
+                unsafe {
+                    backends::loongarch::inner::<R, K, _>(&mut self.state, f);
+                }
+            } else if #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))] {
+                backends::wasm::inner::<R, K, _>(&mut self.state, f);
             } else {
-                f.call(&mut backends::soft::Backend(self));
+                f.call(&mut backends::soft::Backend::<R>::new(&mut self.state));
             }
         }
+
+        #[cfg(all(feature = "paranoid", debug_assertions))]
+        self.reuse_guard.record(start, self.current_block());
+    }
+
+    /// `StreamCipherCoreWrapper` writes a block to its buffer when seeking
+    /// within it, and may use only part of it, see the `paranoid` feature.
+    #[cfg(all(feature = "paranoid", debug_assertions))]
+    fn write_keystream_block(&mut self, block: &mut Block<Self>) {
+        self.reuse_guard.buffer();
+        self.write_keystream_blocks(core::slice::from_mut(block));
     }
 }
 
-impl<R: Unsigned, K: ArraySize> StreamCipherSeekCore for SalsaCore<R, K> {
-    type Counter = u64;
+impl<R: Unsigned, K: ArraySize, C: CounterSize> StreamCipherSeekCore for SalsaCore<R, K, C> {
+    type Counter = C::Counter;
 
     #[inline(always)]
-    fn get_block_pos(&self) -> u64 {
-        cfg_if! {
-            if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
-                (self.state[8] as u64) + ((self.state[5] as u64) << 32)
-            }
-            else {
-                (self.state[8] as u64) + ((self.state[9] as u64) << 32)
-            }
+    fn get_block_pos(&self) -> C::Counter {
+        let mut pos = self.state[8] as u64;
+        if C::USIZE == 8 {
+            pos += (self.state[backends::CTR_HI] as u64) << 32;
         }
+        C::from_pos(pos)
     }
 
     #[inline(always)]
-    fn set_block_pos(&mut self, pos: u64) {
-        cfg_if! {
-            if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
-                self.state[8] = (pos & 0xffff_ffff) as u32;
-                self.state[5] = ((pos >> 32) & 0xffff_ffff) as u32;
-            }
-            else {
-                self.state[8] = (pos & 0xffff_ffff) as u32;
-                self.state[9] = ((pos >> 32) & 0xffff_ffff) as u32;
-            }
+    fn set_block_pos(&mut self, pos: C::Counter) {
+        let pos = C::to_pos(pos);
+        self.state[8] = (pos & 0xffff_ffff) as u32;
+        if C::USIZE == 8 {
+            self.state[backends::CTR_HI] = ((pos >> 32) & 0xffff_ffff) as u32;
         }
+        #[cfg(all(feature = "paranoid", debug_assertions))]
+        self.reuse_guard.seek();
     }
 }
 
 #[cfg(feature = "zeroize")]
 #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
-impl<R: Unsigned, K: ArraySize> Drop for SalsaCore<R, K> {
+impl<R: Unsigned, K: ArraySize, C: CounterSize> Drop for SalsaCore<R, K, C> {
     fn drop(&mut self) {
         self.state.zeroize();
     }
@@ -307,4 +1697,12 @@ impl<R: Unsigned, K: ArraySize> Drop for SalsaCore<R, K> {
 
 #[cfg(feature = "zeroize")]
 #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
-impl<R: Unsigned, K: ArraySize> ZeroizeOnDrop for SalsaCore<R, K> {}
+impl<R: Unsigned, K: ArraySize, C: CounterSize> ZeroizeOnDrop for SalsaCore<R, K, C> {}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned, K: ArraySize, C: CounterSize> Zeroize for SalsaCore<R, K, C> {
+    fn zeroize(&mut self) {
+        SalsaCore::zeroize(self);
+    }
+}
@@ -0,0 +1,199 @@
+//! Wrapper which leaves zeroization of a cipher core to the caller.
+
+use crate::{SalsaCore, Unsigned, STATE_WORDS};
+use cipher::{
+    array::ArraySize, consts::U32, zeroize::Zeroize, Block, BlockSizeUser, IvSizeUser, KeyIvInit,
+    KeySizeUser, StreamCipherClosure, StreamCipherCore, StreamCipherSeekCore,
+};
+use core::{
+    fmt,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+/// Cipher core wrapper which does **not** zeroize the core on drop.
+///
+/// With the `zeroize` feature every [`SalsaCore`][crate::SalsaCore] and
+/// [`XSalsaCore`][crate::XSalsaCore] zeroizes its state when dropped. Code
+/// which creates and drops a cipher per packet can wrap the core to skip
+/// this, and scrub it with [`ManuallyZeroize::zeroize`] at a point of its
+/// choosing instead, e.g. once when a connection is closed.
+///
+/// # Security
+/// A wrapped core which is dropped without calling
+/// [`zeroize`][ManuallyZeroize::zeroize] leaves the key-derived state in
+/// memory, where it may be recovered later, e.g. from a core dump or by a
+/// memory disclosure bug. Only use this wrapper if the key material is
+/// protected by other means or dropping cores shows up in profiles.
+///
+/// The wrapped core is never dropped, so it must not own other resources.
+/// This holds for all cores of this crate.
+///
+/// The inherent methods of the core are available through [`Deref`], so the
+/// wrapped core can be used directly and scrubbed when done:
+///
+/// ```
+/// use salsa20::{ManuallyZeroize, SalsaCore};
+/// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+///
+/// let mut core = ManuallyZeroize::new(SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into()));
+/// let mut buf = [0u8; 64];
+/// core.apply_keystream_blocks(&mut buf).unwrap();
+/// assert_eq!(buf[..4], [0x85, 0x85, 0x3e, 0xc6]);
+/// core.zeroize();
+/// ```
+///
+/// A wrapped core can also be used in a [`StreamCipherCoreWrapper`], which
+/// however gives no mutable access to the core. Such ciphers are never
+/// zeroized:
+///
+/// ```
+/// use salsa20::{ManuallyZeroize, SalsaCore};
+/// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit, StreamCipher, StreamCipherCoreWrapper};
+///
+/// type FastSalsa20 = StreamCipherCoreWrapper<ManuallyZeroize<SalsaCore<U10, U32>>>;
+///
+/// let mut cipher = FastSalsa20::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// let mut buf = [0u8; 4];
+/// cipher.apply_keystream(&mut buf);
+/// assert_eq!(buf, [0x85, 0x85, 0x3e, 0xc6]);
+/// ```
+///
+/// [`StreamCipherCoreWrapper`]: cipher::StreamCipherCoreWrapper
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+pub struct ManuallyZeroize<C: Zeroize>(ManuallyDrop<C>);
+
+impl<C: Zeroize> ManuallyZeroize<C> {
+    /// Wrap `core`, disabling its zeroization on drop.
+    #[inline]
+    pub fn new(core: C) -> Self {
+        Self(ManuallyDrop::new(core))
+    }
+
+    /// Zeroize the wrapped core. It can still be used afterwards, but
+    /// generates the keystream of an all-zero state.
+    #[inline]
+    pub fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+
+    /// Return the wrapped core, which is zeroized on drop again.
+    #[inline]
+    pub fn into_inner(self) -> C {
+        ManuallyDrop::into_inner(self.0)
+    }
+}
+
+/// [`SalsaCore`] which is **not** zeroized on drop.
+///
+/// This is meant for the `scrypt` crate, which runs the Salsa20/8 core on a
+/// transient state for every block it mixes. The state there is derived from
+/// the scrypt working buffer, which the caller has to scrub anyway, so
+/// zeroizing each core on drop only costs time in the innermost loop. See the
+/// security notes of [`ManuallyZeroize`] before using it elsewhere.
+///
+/// ```
+/// use salsa20::SalsaCoreRaw;
+/// use salsa20::cipher::consts::U4;
+///
+/// // Salsa20/8 core of RFC 7914, i.e. the keystream block of the input state
+/// let input = [0x2e2e_2e2e; 16];
+/// let output = SalsaCoreRaw::<U4>::from_raw_state_le(input).block();
+/// # assert_ne!(output[..], [0x2e; 64]);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+pub type SalsaCoreRaw<R, K = U32> = ManuallyZeroize<SalsaCore<R, K>>;
+
+impl<R: Unsigned, K: ArraySize> ManuallyZeroize<SalsaCore<R, K>> {
+    /// Create new core from raw state, see [`SalsaCore::from_raw_state`].
+    #[inline]
+    pub fn from_raw_state(state: [u32; STATE_WORDS]) -> Self {
+        Self::new(SalsaCore::from_raw_state(state))
+    }
+
+    /// Create new core from raw state in canonical little-endian word
+    /// order, see [`SalsaCore::from_raw_state_le`].
+    #[inline]
+    pub fn from_raw_state_le(state: [u32; STATE_WORDS]) -> Self {
+        Self::new(SalsaCore::from_raw_state_le(state))
+    }
+}
+
+impl<C: Zeroize> Deref for ManuallyZeroize<C> {
+    type Target = C;
+
+    #[inline]
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+
+impl<C: Zeroize> DerefMut for ManuallyZeroize<C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.0
+    }
+}
+
+impl<C: Zeroize + Clone> Clone for ManuallyZeroize<C> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<C: Zeroize + fmt::Debug> fmt::Debug for ManuallyZeroize<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ManuallyZeroize").field(&*self.0).finish()
+    }
+}
+
+impl<C: Zeroize + KeySizeUser> KeySizeUser for ManuallyZeroize<C> {
+    type KeySize = C::KeySize;
+}
+
+impl<C: Zeroize + IvSizeUser> IvSizeUser for ManuallyZeroize<C> {
+    type IvSize = C::IvSize;
+}
+
+impl<C: Zeroize + BlockSizeUser> BlockSizeUser for ManuallyZeroize<C> {
+    type BlockSize = C::BlockSize;
+}
+
+impl<C: Zeroize + KeyIvInit> KeyIvInit for ManuallyZeroize<C> {
+    #[inline]
+    fn new(key: &cipher::Key<Self>, iv: &cipher::Iv<Self>) -> Self {
+        Self(ManuallyDrop::new(C::new(key, iv)))
+    }
+}
+
+impl<C: Zeroize + StreamCipherCore> StreamCipherCore for ManuallyZeroize<C> {
+    #[inline(always)]
+    fn remaining_blocks(&self) -> Option<usize> {
+        self.0.remaining_blocks()
+    }
+
+    #[inline(always)]
+    fn process_with_backend(&mut self, f: impl StreamCipherClosure<BlockSize = Self::BlockSize>) {
+        self.0.process_with_backend(f);
+    }
+
+    #[inline(always)]
+    fn write_keystream_block(&mut self, block: &mut Block<Self>) {
+        self.0.write_keystream_block(block);
+    }
+}
+
+impl<C: Zeroize + StreamCipherSeekCore> StreamCipherSeekCore for ManuallyZeroize<C> {
+    type Counter = C::Counter;
+
+    #[inline(always)]
+    fn get_block_pos(&self) -> Self::Counter {
+        self.0.get_block_pos()
+    }
+
+    #[inline(always)]
+    fn set_block_pos(&mut self, pos: Self::Counter) {
+        self.0.set_block_pos(pos);
+    }
+}
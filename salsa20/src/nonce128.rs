@@ -0,0 +1,132 @@
+//! Salsa20 variant with a 128-bit nonce and no block counter
+
+use super::{Key, SalsaCore, Unsigned};
+use cipher::{
+    array::Array,
+    consts::{U10, U16, U32, U64, U8},
+    Block, BlockSizeUser, IvSizeUser, KeyIvInit, KeySizeUser, StreamCipherClosure,
+    StreamCipherCore, StreamCipherCoreWrapper, StreamCipherSeekCore,
+};
+
+use core::fmt;
+
+#[cfg(feature = "reduced-round")]
+use cipher::consts::{U4, U6};
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::ZeroizeOnDrop;
+
+/// Nonce type used by [`Salsa20Nonce128`].
+pub type Nonce128 = Array<u8, U16>;
+
+/// Salsa20 variant which uses all 128 bits of state words 6 to 9 as nonce.
+///
+/// The 64-bit block counter of [`Salsa20`][crate::Salsa20] is replaced by the
+/// upper half of the nonce, so the keystream for each nonce is **a single
+/// 64-byte block**. Applying more keystream returns an error (or panics with
+/// the panicking methods). This fits protocols which derive one block per
+/// nonce, e.g. a one-time key for a MAC, but a fresh nonce has to be used for
+/// every 64 bytes of data.
+///
+/// The keystream for nonce `n` equals the [`Salsa20`][crate::Salsa20]
+/// keystream block at position `u64::from_le_bytes(n[8..])` for the 64-bit
+/// nonce `n[..8]`.
+///
+/// # Example
+/// ```
+/// use salsa20::Salsa20Nonce128;
+/// use salsa20::cipher::{KeyIvInit, StreamCipher};
+///
+/// let mut cipher = Salsa20Nonce128::new(&[0x42; 32].into(), &[0x24; 16].into());
+///
+/// let mut buf = [0u8; 64];
+/// cipher.apply_keystream(&mut buf);
+/// // The keystream of the nonce is exhausted
+/// assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+/// ```
+pub type Salsa20Nonce128 = StreamCipherCoreWrapper<SalsaNonce128Core<U10>>;
+/// Salsa20/12 variant of [`Salsa20Nonce128`] (*not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa12Nonce128 = StreamCipherCoreWrapper<SalsaNonce128Core<U6>>;
+/// Salsa20/8 variant of [`Salsa20Nonce128`] (*not recommended*)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa8Nonce128 = StreamCipherCoreWrapper<SalsaNonce128Core<U4>>;
+
+/// The Salsa core function with a 128-bit nonce, see [`Salsa20Nonce128`].
+///
+/// Seeking is not supported, as the keystream consists of a single block.
+pub struct SalsaNonce128Core<R: Unsigned> {
+    /// Core whose block position holds the upper half of the nonce.
+    inner: SalsaCore<R, U32>,
+    /// Upper half of the nonce, i.e. the block position of the only block.
+    nonce_hi: u64,
+}
+
+impl<R: Unsigned> Clone for SalsaNonce128Core<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            nonce_hi: self.nonce_hi,
+        }
+    }
+}
+
+/// The key-derived state is never printed.
+impl<R: Unsigned> fmt::Debug for SalsaNonce128Core<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SalsaNonce128Core")
+            .field("double_rounds", &R::USIZE)
+            .field("key_size", &U32::USIZE)
+            .field("remaining_blocks", &self.remaining_blocks())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Unsigned> KeySizeUser for SalsaNonce128Core<R> {
+    type KeySize = U32;
+}
+
+impl<R: Unsigned> IvSizeUser for SalsaNonce128Core<R> {
+    type IvSize = U16;
+}
+
+impl<R: Unsigned> BlockSizeUser for SalsaNonce128Core<R> {
+    type BlockSize = U64;
+}
+
+impl<R: Unsigned> KeyIvInit for SalsaNonce128Core<R> {
+    #[inline]
+    fn new(key: &Key<U32>, iv: &Nonce128) -> Self {
+        let (nonce_lo, nonce_hi) = iv.split::<U8>();
+        let nonce_hi = u64::from_le_bytes(nonce_hi.into());
+        Self {
+            inner: SalsaCore::new_with_counter(key, &nonce_lo, nonce_hi),
+            nonce_hi,
+        }
+    }
+}
+
+impl<R: Unsigned> StreamCipherCore for SalsaNonce128Core<R> {
+    #[inline(always)]
+    fn remaining_blocks(&self) -> Option<usize> {
+        // Generating the block moves the counter words away from the nonce,
+        // wrapping around if the upper half of the nonce is `u64::MAX`
+        Some(usize::from(self.inner.get_block_pos() == self.nonce_hi))
+    }
+
+    #[inline(always)]
+    fn process_with_backend(&mut self, f: impl StreamCipherClosure<BlockSize = Self::BlockSize>) {
+        self.inner.process_with_backend(f);
+    }
+
+    #[inline(always)]
+    fn write_keystream_block(&mut self, block: &mut Block<Self>) {
+        self.inner.write_keystream_block(block);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned> ZeroizeOnDrop for SalsaNonce128Core<R> {}
@@ -0,0 +1,63 @@
+//! Parallel keystream application on the [`rayon`] thread pool.
+
+use crate::{SalsaCore, Unsigned};
+use cipher::{array::ArraySize, StreamCipherSeekCore};
+use rayon::prelude::*;
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+/// Number of blocks processed by each task. Large enough to amortize the
+/// task overhead, small enough to balance the load between the threads.
+const CHUNK_BLOCKS: usize = 1024;
+
+/// Chunk size in bytes, a multiple of the 64-byte block size.
+const CHUNK_SIZE: usize = 64 * CHUNK_BLOCKS;
+
+impl<R: Unsigned, K: ArraySize> SalsaCore<R, K> {
+    /// Apply keystream to `buf`, starting at the current block, on the
+    /// [`rayon`] thread pool.
+    ///
+    /// The buffer is split into block-aligned chunks and each chunk is
+    /// processed by a copy of the core seeked to the first block of the
+    /// chunk, so the result equals that of applying the keystream
+    /// sequentially. Since the core is only copied, its block position is
+    /// **not** advanced: seek past the processed data before using the core
+    /// for other data, or the keystream is reused.
+    ///
+    /// ```
+    /// use salsa20::SalsaCore;
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut buf = vec![0u8; 1 << 20];
+    /// core.par_apply_keystream(&mut buf);
+    /// assert_eq!(buf[..4], [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// # Panics
+    /// If processing `buf` would overflow the 64-bit block counter. `buf` is
+    /// left untouched in that case.
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn par_apply_keystream(&self, buf: &mut [u8]) {
+        let blocks = buf.len().div_ceil(64) as u64;
+        assert!(blocks <= self.remaining_blocks(), "block counter overflow");
+
+        // The raw state is shared instead of the core, whose round and key
+        // size parameters are not `Sync`
+        #[allow(unused_mut)]
+        let mut state = self.canonical_state();
+        let start = self.get_block_pos();
+        buf.par_chunks_mut(CHUNK_SIZE)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let mut core = Self::from_raw_state(state);
+                core.set_block_pos(start + (i * CHUNK_BLOCKS) as u64);
+                // The overflow check above covers all chunks
+                core.keystream_xor_inout(chunk.into()).unwrap();
+            });
+
+        #[cfg(feature = "zeroize")]
+        state.zeroize();
+    }
+}
@@ -0,0 +1,163 @@
+//! Detection of keystream reuse in debug builds, see the `paranoid` feature.
+
+use core::ops::Range;
+
+/// Range of block positions whose keystream an instance already used.
+///
+/// `StreamCipherCoreWrapper` writes a block to its buffer to seek within it
+/// or to apply the keystream to less than a block, and may use only part of
+/// it. Such a block only counts as used once the keystream continues with
+/// the next block without seeking, so seeking within the buffered block,
+/// e.g. to the current position, and generating it again is allowed.
+///
+/// Only the most recent contiguous range is tracked: using keystream which
+/// neither overlaps nor adjoins it starts a new range. This never reports
+/// false positives, but may miss reuse within a buffered block or of older
+/// ranges. Those show up in the [`ConsumedPositions`] audit instead.
+#[derive(Clone)]
+pub(crate) struct ReuseGuard {
+    start: u64,
+    end: u64,
+    /// Block in the buffer of the wrapper, if it was written without seeking
+    buffered: Option<u64>,
+    /// Whether the block position was set since the last generated block
+    seeked: bool,
+    /// Whether the next generated block is written to the buffer
+    buffering: bool,
+    consumed: ConsumedPositions,
+}
+
+impl ReuseGuard {
+    pub(crate) const fn new() -> Self {
+        Self {
+            start: 0,
+            end: 0,
+            buffered: None,
+            seeked: false,
+            buffering: false,
+            consumed: ConsumedPositions {
+                blocks: 0,
+                digest: 0,
+                last: 0..0,
+            },
+        }
+    }
+
+    /// Record that the block position was set.
+    pub(crate) fn seek(&mut self) {
+        self.seeked = true;
+    }
+
+    /// Record that the next generated block is written to the buffer of the
+    /// wrapper.
+    pub(crate) fn buffer(&mut self) {
+        self.buffering = true;
+    }
+
+    /// Record that the keystream blocks from `start` up to `end` were
+    /// generated, and panic if any of them was already used.
+    #[track_caller]
+    pub(crate) fn record(&mut self, start: u64, end: u64) {
+        self.consumed.record(start, end);
+        let seeked = core::mem::take(&mut self.seeked);
+        let buffering = core::mem::take(&mut self.buffering);
+
+        // Continuing without seeking means the wrapper used up its buffer
+        if let Some(pos) = self.buffered.take() {
+            if !seeked {
+                self.insert(pos, pos + 1);
+            }
+        }
+
+        let reused = start.max(self.start)..end.min(self.end);
+        assert!(
+            reused.is_empty(),
+            "keystream reuse: block {} was already generated by this cipher instance",
+            reused.start,
+        );
+
+        if !buffering {
+            self.insert(start, end);
+        } else if !seeked {
+            self.buffered = Some(start);
+        }
+    }
+
+    /// Add the blocks from `start` up to `end` to the tracked range.
+    fn insert(&mut self, start: u64, end: u64) {
+        if start <= self.end && end >= self.start {
+            self.start = self.start.min(start);
+            self.end = self.end.max(end);
+        } else {
+            self.start = start;
+            self.end = end;
+        }
+    }
+
+    /// Get the audit of all block positions generated so far.
+    pub(crate) fn consumed(&self) -> ConsumedPositions {
+        self.consumed.clone()
+    }
+}
+
+/// Audit of the keystream block positions a cipher instance generated since
+/// it was created, re-keyed or zeroized.
+///
+/// The positions are summarized by their number and an order-independent
+/// digest, so keeping the audit costs no memory per block. Reusing a block
+/// position, e.g. after seeking back past the range checked by the
+/// `paranoid` feature, adds the position to the digest a second time, so the
+/// digest no longer matches [`digest_of`][Self::digest_of] the distinct
+/// positions the caller meant to use.
+///
+/// The digest is meant for debugging seek bugs, not as a cryptographic hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsumedPositions {
+    blocks: u64,
+    digest: u64,
+    last: Range<u64>,
+}
+
+impl ConsumedPositions {
+    /// Number of keystream blocks generated, counting reused positions
+    /// every time they were generated.
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Order-independent digest of the generated block positions.
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// Most recently generated range of block positions.
+    pub fn last(&self) -> Range<u64> {
+        self.last.clone()
+    }
+
+    /// Compute the digest of the block positions `positions`, e.g. to
+    /// compare it with [`digest`][Self::digest] after processing a message.
+    pub fn digest_of(positions: impl IntoIterator<Item = u64>) -> u64 {
+        positions
+            .into_iter()
+            .fold(0, |digest, pos| digest.wrapping_add(mix(pos)))
+    }
+
+    fn record(&mut self, start: u64, end: u64) {
+        // The counter of `Salsa20Nonce128` may wrap around to zero
+        let n = end.wrapping_sub(start);
+        let positions = (0..n).map(|i| start.wrapping_add(i));
+        self.blocks = self.blocks.wrapping_add(n);
+        self.digest = self.digest.wrapping_add(Self::digest_of(positions));
+        self.last = start..end;
+    }
+}
+
+/// The SplitMix64 finalizer, which spreads consecutive positions over the
+/// whole digest.
+fn mix(pos: u64) -> u64 {
+    let mut z = pos.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
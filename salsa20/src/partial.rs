@@ -0,0 +1,43 @@
+//! Applying keystream to a prefix of a buffer.
+
+use cipher::{StreamCipher, StreamCipherError};
+
+/// Extension trait applying keystream to at most a given number of bytes.
+///
+/// Implemented for every [`StreamCipher`], e.g. [`Salsa20`][crate::Salsa20]
+/// and [`XSalsa20`][crate::XSalsa20].
+pub trait ApplyKeystreamPartial: StreamCipher {
+    /// Apply keystream to the first `n` bytes of `buf`, or to all of `buf` if
+    /// it is shorter, and return the number of bytes processed.
+    ///
+    /// The keystream position ends up exactly at the boundary, also in the
+    /// middle of a block, so that the next call continues from there. This
+    /// fits framed protocols which know the length of the current frame, but
+    /// read data in larger chunks.
+    ///
+    /// Returns an error if the keystream would be exhausted, in which case
+    /// `buf` and the keystream position are left untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use salsa20::{ApplyKeystreamPartial, Salsa20};
+    /// use salsa20::cipher::{KeyIvInit, StreamCipherSeek};
+    ///
+    /// let mut cipher = Salsa20::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let mut buf = [0u8; 8];
+    /// assert_eq!(cipher.apply_keystream_partial(&mut buf, 4).unwrap(), 4);
+    /// assert_eq!(buf, [0x85, 0x85, 0x3e, 0xc6, 0, 0, 0, 0]);
+    /// assert_eq!(cipher.current_pos::<u64>(), 4);
+    /// ```
+    fn apply_keystream_partial(
+        &mut self,
+        buf: &mut [u8],
+        n: usize,
+    ) -> Result<usize, StreamCipherError> {
+        let n = n.min(buf.len());
+        self.try_apply_keystream(&mut buf[..n])?;
+        Ok(n)
+    }
+}
+
+impl<C: StreamCipher + ?Sized> ApplyKeystreamPartial for C {}
@@ -0,0 +1,220 @@
+//! Random number generator built on the Salsa keystream.
+
+use crate::{Key, Nonce, SalsaCore, Unsigned};
+use cipher::{
+    array::ArraySize,
+    consts::{U10, U32},
+    KeyIvInit, StreamCipherSeekCore,
+};
+
+use core::{convert::Infallible, fmt};
+use rand_core::{CryptoRng, RngCore, SeedableRng, TryCryptoRng, TryRngCore};
+
+#[cfg(feature = "reduced-round")]
+use cipher::consts::{U4, U6};
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Number of 32-bit words in a keystream block
+const BLOCK_WORDS: usize = 16;
+
+/// Salsa20/8 random number generator
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa8Rng = SalsaRng<U4, U32>;
+
+/// Salsa20/12 random number generator
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa12Rng = SalsaRng<U6, U32>;
+
+/// Salsa20/20 random number generator
+pub type Salsa20Rng = SalsaRng<U10, U32>;
+
+/// Random number generator which outputs the Salsa keystream of its seed.
+///
+/// The seed is used as the cipher key with an all-zero nonce, so the output is
+/// identical to the keystream of the corresponding [`SalsaCore`].
+///
+/// One keystream block is buffered at a time, so that [`RngCore::next_u32`] and
+/// [`RngCore::next_u64`] do not discard the rest of a block. Like the `rand`
+/// block RNGs, [`RngCore::fill_bytes`] consumes whole 32-bit words, discarding
+/// the unused bytes of a trailing partial word.
+///
+/// # Security
+/// The generator implements [`CryptoRng`], as its output can not be told
+/// apart from random without knowing the seed. It is however fully
+/// deterministic: the same seed always gives the same output, and anyone
+/// who learns the seed can reproduce all of it. This makes it suitable as a
+/// deterministic entropy source, e.g. to replay tests or simulations from a
+/// recorded seed, but the seed itself has to come from a real entropy
+/// source when the output must be unpredictable. Like the ciphers it
+/// provides no authenticity.
+///
+/// # Example
+/// ```
+/// use salsa20::Salsa20Rng;
+/// use salsa20::rand_core::{RngCore, SeedableRng};
+///
+/// let mut rng = Salsa20Rng::from_seed([42u8; 32].into());
+/// let x = rng.next_u32();
+///
+/// // seek back to the first word
+/// rng.set_word_pos(0);
+/// assert_eq!(rng.next_u32(), x);
+/// ```
+pub struct SalsaRng<R: Unsigned, K: ArraySize> {
+    core: SalsaCore<R, K>,
+    /// Keystream of the block preceding the core's block position
+    buffer: [u32; BLOCK_WORDS],
+    /// Index of the next unused word in `buffer`
+    index: usize,
+}
+
+impl<R: Unsigned, K: ArraySize> SalsaRng<R, K> {
+    /// Get the offset from the start of the stream, in 32-bit words.
+    ///
+    /// The 64-bit block counter makes this a 68-bit number.
+    #[inline]
+    pub fn get_word_pos(&self) -> u128 {
+        let pos = (u128::from(self.core.get_block_pos()) << 4) + self.index as u128;
+        pos.wrapping_sub(BLOCK_WORDS as u128) & ((1 << 68) - 1)
+    }
+
+    /// Set the offset from the start of the stream, in 32-bit words.
+    ///
+    /// Only the least significant 68 bits of `word_offset` are used.
+    #[inline]
+    pub fn set_word_pos(&mut self, word_offset: u128) {
+        let index = (word_offset as usize) % BLOCK_WORDS;
+        self.core.set_block_pos((word_offset >> 4) as u64);
+        self.core.reset_reuse_guard();
+        self.index = BLOCK_WORDS;
+        if index != 0 {
+            self.refill();
+            self.index = index;
+        }
+    }
+
+    /// Replace the seed, restarting the output at word position 0 as if the
+    /// generator was created with [`SeedableRng::from_seed`].
+    ///
+    /// With the `zeroize` feature the old state is zeroized first.
+    pub fn reseed(&mut self, seed: Key<K>) {
+        self.core.rekey(&seed, &Nonce::default());
+        #[cfg(feature = "zeroize")]
+        self.buffer.zeroize();
+        self.index = BLOCK_WORDS;
+    }
+
+    /// Generate the next keystream block into the buffer.
+    fn refill(&mut self) {
+        let block = self.core.block();
+        for (word, chunk) in self.buffer.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        self.index = 0;
+    }
+
+    /// Copy buffered words into `dest`, returning the part left unfilled.
+    fn drain<'a>(&mut self, mut dest: &'a mut [u8]) -> &'a mut [u8] {
+        while self.index < BLOCK_WORDS && !dest.is_empty() {
+            let n = dest.len().min(4);
+            let (head, rest) = dest.split_at_mut(n);
+            head.copy_from_slice(&self.buffer[self.index].to_le_bytes()[..n]);
+            self.index += 1;
+            dest = rest;
+        }
+        dest
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> SeedableRng for SalsaRng<R, K> {
+    type Seed = Key<K>;
+
+    #[inline]
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            core: SalsaCore::new(&seed, &Nonce::default()),
+            buffer: [0; BLOCK_WORDS],
+            index: BLOCK_WORDS,
+        }
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> RngCore for SalsaRng<R, K> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= BLOCK_WORDS {
+            self.refill();
+        }
+        let res = self.buffer[self.index];
+        self.index += 1;
+        res
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let lo = u64::from(self.next_u32());
+        let hi = u64::from(self.next_u32());
+        (hi << 32) | lo
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let dest = self.drain(dest);
+
+        // Whole blocks are written directly, bypassing the buffer
+        let (blocks, tail) = dest.split_at_mut(dest.len() - dest.len() % 64);
+        let _ = self.core.generate_keystream(blocks);
+
+        if !tail.is_empty() {
+            self.refill();
+            self.drain(tail);
+        }
+    }
+}
+
+impl<R: Unsigned, K: ArraySize> TryRngCore for SalsaRng<R, K> {
+    type Error = Infallible;
+
+    #[inline]
+    fn try_next_u32(&mut self) -> Result<u32, Self::Error> {
+        Ok(self.next_u32())
+    }
+
+    #[inline]
+    fn try_next_u64(&mut self) -> Result<u64, Self::Error> {
+        Ok(self.next_u64())
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Self::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The output is deterministic given the seed, see the security notes of
+/// [`SalsaRng`].
+impl<R: Unsigned, K: ArraySize> CryptoRng for SalsaRng<R, K> {}
+impl<R: Unsigned, K: ArraySize> TryCryptoRng for SalsaRng<R, K> {}
+
+// Custom Debug implementation that does not expose the internal state
+impl<R: Unsigned, K: ArraySize> fmt::Debug for SalsaRng<R, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SalsaRng { ... }")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned, K: ArraySize> Drop for SalsaRng<R, K> {
+    fn drop(&mut self) {
+        self.buffer.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned, K: ArraySize> ZeroizeOnDrop for SalsaRng<R, K> {}
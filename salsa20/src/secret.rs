@@ -0,0 +1,144 @@
+//! Key and nonce containers which zeroize their contents on drop.
+
+use cipher::{
+    array::{Array, ArraySize},
+    zeroize::{Zeroize, ZeroizeOnDrop},
+};
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+macro_rules! secret_array {
+    ($(#[$attr:meta])* $name:ident, $what:literal) => {
+        $(#[$attr])*
+        #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+        #[repr(transparent)]
+        pub struct $name<N: ArraySize>(Array<u8, N>);
+
+        impl<N: ArraySize> $name<N> {
+            #[doc = concat!("Wrap `bytes`, taking ownership of the ", $what, ".")]
+            #[inline]
+            pub fn new(bytes: Array<u8, N>) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl<N: ArraySize> Default for $name<N> {
+            #[inline]
+            fn default() -> Self {
+                Self(Array::default())
+            }
+        }
+
+        impl<N: ArraySize> Clone for $name<N> {
+            #[inline]
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl<N: ArraySize> From<Array<u8, N>> for $name<N> {
+            #[inline]
+            fn from(bytes: Array<u8, N>) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl<N: ArraySize> Deref for $name<N> {
+            type Target = Array<u8, N>;
+
+            #[inline]
+            fn deref(&self) -> &Array<u8, N> {
+                &self.0
+            }
+        }
+
+        impl<N: ArraySize> DerefMut for $name<N> {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut Array<u8, N> {
+                &mut self.0
+            }
+        }
+
+        impl<N: ArraySize> AsRef<Array<u8, N>> for $name<N> {
+            #[inline]
+            fn as_ref(&self) -> &Array<u8, N> {
+                &self.0
+            }
+        }
+
+        impl<N: ArraySize> AsRef<[u8]> for $name<N> {
+            #[inline]
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        #[doc = concat!("The ", $what, " is never printed.")]
+        impl<N: ArraySize> fmt::Debug for $name<N> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+
+        impl<N: ArraySize> Zeroize for $name<N> {
+            #[inline]
+            fn zeroize(&mut self) {
+                self.0.as_mut_slice().zeroize();
+            }
+        }
+
+        impl<N: ArraySize> Drop for $name<N> {
+            #[inline]
+            fn drop(&mut self) {
+                self.zeroize();
+            }
+        }
+
+        impl<N: ArraySize> ZeroizeOnDrop for $name<N> {}
+    };
+}
+
+secret_array!(
+    /// A key which is zeroized on drop.
+    ///
+    /// [`Key`][crate::Key] is a plain [`Array`], so key bytes passed to
+    /// [`KeyIvInit::new`][cipher::KeyIvInit::new] stay in the memory of the
+    /// caller until it is overwritten. This wrapper zeroizes the key when
+    /// it is dropped and dereferences to the wrapped array, so it can be
+    /// passed to `new` directly:
+    ///
+    /// ```
+    /// use salsa20::{SecretKey, SecretNonce, Salsa20};
+    /// use salsa20::cipher::{KeyIvInit, StreamCipher};
+    ///
+    /// let key = SecretKey::new([0x42; 32].into());
+    /// let nonce = SecretNonce::new([0x24; 8].into());
+    /// let mut cipher = Salsa20::new(&key, &nonce);
+    ///
+    /// let mut buf = [0u8; 4];
+    /// cipher.apply_keystream(&mut buf);
+    /// assert_eq!(buf, [0x85, 0x85, 0x3e, 0xc6]);
+    /// ```
+    ///
+    /// # Security
+    /// This is best-effort: moving the wrapper, or the compiler keeping
+    /// copies in registers or on the stack, may leave key bytes behind which
+    /// are not zeroized. Keeping the wrapper in one place, e.g. in a `Box`,
+    /// reduces the number of copies.
+    SecretKey,
+    "key"
+);
+
+secret_array!(
+    /// A nonce which is zeroized on drop.
+    ///
+    /// Nonces are usually not secret, but some protocols derive them from
+    /// key material. Like [`SecretKey`] this dereferences to the wrapped
+    /// array, so it can be passed to
+    /// [`KeyIvInit::new`][cipher::KeyIvInit::new] directly, and the same
+    /// caveats apply.
+    SecretNonce,
+    "nonce"
+);
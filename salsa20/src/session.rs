@@ -0,0 +1,113 @@
+//! Buffered keystream for many small messages.
+
+use crate::{Key, Nonce, SalsaCore, Unsigned};
+use cipher::{
+    array::ArraySize,
+    consts::{U10, U32},
+    KeyIvInit,
+};
+
+#[cfg(feature = "reduced-round")]
+use cipher::consts::{U4, U6};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::Zeroize;
+
+/// Salsa20/8 session (*not recommended*), see [`SalsaSession`].
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa8Session = SalsaSession<U4, U32>;
+
+/// Salsa20/12 session (*not recommended*), see [`SalsaSession`].
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type Salsa12Session = SalsaSession<U6, U32>;
+
+/// Salsa20/20 session (**recommended**), see [`SalsaSession`].
+pub type Salsa20Session = SalsaSession<U10, U32>;
+
+/// Size of the keystream buffer, a batch of four blocks.
+const SCRATCH_SIZE: usize = 4 * 64;
+
+/// Salsa cipher for streams of many small messages, which generates the
+/// keystream in batches of four blocks and buffers it across calls.
+///
+/// [`StreamCipher::apply_keystream`][cipher::StreamCipher::apply_keystream]
+/// invokes the backend for every message, and buffers at most one block. A
+/// session instead invokes it once per 256 bytes of keystream, so messages
+/// much shorter than a block share the cost of generating it, and the backend
+/// can compute the blocks of a batch in parallel.
+///
+/// The keystream is continuous across messages: encrypting several messages
+/// gives the same result as encrypting their concatenation with
+/// [`Salsa20`][crate::Salsa20].
+///
+/// # Example
+/// ```
+/// use salsa20::Salsa20Session;
+///
+/// let mut session = Salsa20Session::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// let (mut a, mut b) = ([0u8; 2], [0u8; 2]);
+/// session.encrypt(&mut a);
+/// session.encrypt(&mut b);
+/// assert_eq!([a, b], [[0x85, 0x85], [0x3e, 0xc6]]);
+/// ```
+pub struct SalsaSession<R: Unsigned, K: ArraySize> {
+    core: SalsaCore<R, K>,
+    scratch: [u8; SCRATCH_SIZE],
+    /// Number of bytes of `scratch` already used.
+    pos: usize,
+}
+
+impl<R: Unsigned, K: ArraySize> SalsaSession<R, K> {
+    /// Create new session with the given key and nonce.
+    pub fn new(key: &Key<K>, nonce: &Nonce) -> Self {
+        Self::from_core(SalsaCore::new(key, nonce))
+    }
+
+    /// Create new session which starts at the current block of `core`.
+    pub fn from_core(core: SalsaCore<R, K>) -> Self {
+        Self {
+            core,
+            scratch: [0; SCRATCH_SIZE],
+            pos: SCRATCH_SIZE,
+        }
+    }
+
+    /// Encrypt or decrypt `buf` in place with the next keystream bytes.
+    ///
+    /// # Panics
+    /// If the keystream is exhausted.
+    pub fn encrypt(&mut self, mut buf: &mut [u8]) {
+        while !buf.is_empty() {
+            if self.pos == SCRATCH_SIZE {
+                self.refill();
+            }
+            let ks = &self.scratch[self.pos..];
+            let n = buf.len().min(ks.len());
+            for (b, k) in buf[..n].iter_mut().zip(ks) {
+                *b ^= k;
+            }
+            self.pos += n;
+            buf = &mut buf[n..];
+        }
+    }
+
+    /// Generate the next batch of keystream into the scratch buffer.
+    fn refill(&mut self) {
+        let blocks = self.core.remaining_blocks().min(4) as usize;
+        assert!(blocks > 0, "keystream exhausted");
+        // Near the end of the keystream only the remaining blocks are
+        // generated, at the end of the buffer
+        self.pos = SCRATCH_SIZE - 64 * blocks;
+        let _ = self.core.generate_keystream(&mut self.scratch[self.pos..]);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned, K: ArraySize> Drop for SalsaSession<R, K> {
+    fn drop(&mut self) {
+        self.scratch.zeroize();
+    }
+}
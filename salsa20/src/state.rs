@@ -0,0 +1,66 @@
+//! Serializable checkpoint of the Salsa state.
+
+use crate::{SalsaCore, Unsigned, STATE_WORDS};
+use cipher::array::ArraySize;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "zeroize")]
+use cipher::zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Checkpoint of a [`SalsaCore`], including its block position, which can be
+/// serialized to resume a long-running stream after a process restart.
+///
+/// The state words are always stored in the canonical Salsa20 order, so a
+/// checkpoint written on a target which uses a different internal layout
+/// (e.g. x86 with the SIMD backends) can be restored on any other target.
+///
+/// The checkpoint does not record the number of rounds or the key size: it
+/// must be restored into a core of the same type it was taken from.
+///
+/// The checkpoint contains the key! It must be stored with the same care as
+/// the key itself.
+///
+/// # Example
+/// ```
+/// use salsa20::{SalsaCore, SalsaState};
+/// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit, StreamCipherSeekCore};
+///
+/// let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+/// core.set_block_pos(1234);
+///
+/// let state: SalsaState = core.to_state();
+/// let restored = SalsaCore::<U10, U32>::from_state(&state);
+/// assert_eq!(restored.get_block_pos(), 1234);
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalsaState {
+    state: [u32; STATE_WORDS],
+}
+
+impl<R: Unsigned, K: ArraySize> SalsaCore<R, K> {
+    /// Take a checkpoint of the current state, see [`SalsaState`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn to_state(&self) -> SalsaState {
+        SalsaState {
+            state: self.canonical_state(),
+        }
+    }
+
+    /// Restore a core from a checkpoint taken with [`SalsaCore::to_state`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn from_state(state: &SalsaState) -> Self {
+        Self::from_raw_state(state.state)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Drop for SalsaState {
+    fn drop(&mut self) {
+        self.state.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl ZeroizeOnDrop for SalsaState {}
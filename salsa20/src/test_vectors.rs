@@ -0,0 +1,294 @@
+//! Known-answer test harness for conformance testing.
+
+use crate::{SalsaCore, Unsigned};
+use cipher::{
+    array::ArraySize,
+    consts::{U16, U32},
+    InvalidLength,
+};
+use hex_literal::hex;
+
+/// Compute keystream block number `block` of Salsa with `R` double rounds,
+/// e.g. `U10` for Salsa20/20, for a 16 or 32 byte `key` and an 8 byte `nonce`.
+///
+/// This gives external conformance tools direct access to the raw keystream
+/// at any block position, e.g. to run the full ECRYPT test vector sets, which
+/// list keystream excerpts at several offsets for each key and nonce.
+///
+/// Returns [`InvalidLength`] for other key or nonce lengths.
+///
+/// # Example
+/// ```
+/// use salsa20::cipher::consts::U10;
+/// use salsa20::test_vectors::keystream_block;
+///
+/// let block = keystream_block::<U10>(&[0x42; 32], &[0x24; 8], 0).unwrap();
+/// assert_eq!(block[..4], [0x85, 0x85, 0x3e, 0xc6]);
+/// ```
+pub fn keystream_block<R: Unsigned>(
+    key: &[u8],
+    nonce: &[u8],
+    block: u64,
+) -> Result<[u8; 64], InvalidLength> {
+    fn generate<R: Unsigned, K: ArraySize>(
+        key: &[u8],
+        nonce: &[u8],
+        block: u64,
+    ) -> Result<[u8; 64], InvalidLength> {
+        let mut core = SalsaCore::<R, K>::builder(key, nonce)
+            .counter(block)
+            .build()?;
+        Ok(core.block().into())
+    }
+
+    match key.len() {
+        16 => generate::<R, U16>(key, nonce, block),
+        32 => generate::<R, U32>(key, nonce, block),
+        _ => Err(InvalidLength),
+    }
+}
+
+/// Fill `out` with the first `out.len() / 64` keystream blocks of Salsa with
+/// `R` double rounds for a 16 or 32 byte `key` and an 8 byte `nonce`.
+///
+/// The format is stable and meant for bulk comparison with other
+/// implementations, e.g. the `crypto_stream_salsa20` function of libsodium
+/// or the reference code of Salsa20: block `i` is the keystream for block
+/// counter `i`, starting at 0, stored at bytes `64 * i..64 * (i + 1)`. Each
+/// block consists of the 16 output words encoded as little-endian, as in the
+/// Salsa20 specification, so the output is the keystream itself.
+///
+/// Returns [`InvalidLength`] for other key or nonce lengths, or if the length
+/// of `out` is not a multiple of 64.
+///
+/// # Example
+/// ```
+/// use salsa20::cipher::consts::U10;
+/// use salsa20::test_vectors::{export_keystream, keystream_block};
+///
+/// let mut out = [0u8; 3 * 64];
+/// export_keystream::<U10>(&[0x42; 32], &[0x24; 8], &mut out).unwrap();
+/// let block = keystream_block::<U10>(&[0x42; 32], &[0x24; 8], 2).unwrap();
+/// assert_eq!(out[128..], block);
+/// ```
+pub fn export_keystream<R: Unsigned>(
+    key: &[u8],
+    nonce: &[u8],
+    out: &mut [u8],
+) -> Result<(), InvalidLength> {
+    fn generate<R: Unsigned, K: ArraySize>(
+        key: &[u8],
+        nonce: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), InvalidLength> {
+        let mut core = SalsaCore::<R, K>::builder(key, nonce).build()?;
+        let _ = core.generate_keystream(out);
+        Ok(())
+    }
+
+    if out.len() % 64 != 0 {
+        return Err(InvalidLength);
+    }
+    match key.len() {
+        16 => generate::<R, U16>(key, nonce, out),
+        32 => generate::<R, U32>(key, nonce, out),
+        _ => Err(InvalidLength),
+    }
+}
+
+/// Salsa20/20 test vector: a key and nonce with keystream excerpts.
+#[derive(Clone, Copy, Debug)]
+pub struct TestVector {
+    /// Name of the vector in the ECRYPT test vector files.
+    pub name: &'static str,
+    /// 16 or 32 byte key.
+    pub key: &'static [u8],
+    /// Nonce.
+    pub iv: [u8; 8],
+    /// Expected keystream as pairs of block position and block.
+    pub blocks: &'static [(u64, [u8; 64])],
+}
+
+/// Salsa20/20 vectors from the ECRYPT `verified.test-vectors` files, with
+/// the keystream excerpts at bytes 0, 192, 256 and 448, i.e. blocks 0, 3, 4
+/// and 7.
+pub const ECRYPT_SALSA20: &[TestVector] = &[
+    TestVector {
+        name: "128-bit key, set 1, vector 0",
+        key: &hex!("80000000000000000000000000000000"),
+        iv: hex!("0000000000000000"),
+        blocks: &[
+            (
+                0,
+                hex!(
+                    "4DFA5E481DA23EA09A31022050859936"
+                    "DA52FCEE218005164F267CB65F5CFD7F"
+                    "2B4F97E0FF16924A52DF269515110A07"
+                    "F9E460BC65EF95DA58F740B7D1DBB0AA"
+                ),
+            ),
+            (
+                3,
+                hex!(
+                    "DA9C1581F429E0A00F7D67E23B730676"
+                    "783B262E8EB43A25F55FB90B3E753AEF"
+                    "8C6713EC66C51881111593CCB3E8CB8F"
+                    "8DE124080501EEEB389C4BCB6977CF95"
+                ),
+            ),
+            (
+                4,
+                hex!(
+                    "7D5789631EB4554400E1E025935DFA7B"
+                    "3E9039D61BDC58A8697D36815BF1985C"
+                    "EFDF7AE112E5BB81E37ECF0616CE7147"
+                    "FC08A93A367E08631F23C03B00A8DA2F"
+                ),
+            ),
+            (
+                7,
+                hex!(
+                    "B375703739DACED4DD4059FD71C3C47F"
+                    "C2F9939670FAD4A46066ADCC6A564578"
+                    "3308B90FFB72BE04A6B147CBE38CC0C3"
+                    "B9267C296A92A7C69873F9F263BE9703"
+                ),
+            ),
+        ],
+    },
+    TestVector {
+        name: "128-bit key, set 6, vector 0",
+        key: &hex!("0053A6F94C9FF24598EB3E91E4378ADD"),
+        iv: hex!("0D74DB42A91077DE"),
+        blocks: &[
+            (
+                0,
+                hex!(
+                    "05E1E7BEB697D999656BF37C1B978806"
+                    "735D0B903A6007BD329927EFBE1B0E2A"
+                    "8137C1AE291493AA83A821755BEE0B06"
+                    "CD14855A67E46703EBF8F3114B584CBA"
+                ),
+            ),
+            (
+                3,
+                hex!(
+                    "DB55B236494C1EFB021D6684A539E9B6"
+                    "092D4621075A4490AB8CAB704934725E"
+                    "2A5BF35B30E870AB35D000AD7FB40F88"
+                    "82E14E2148138CC57D4F0E6BE7C757F4"
+                ),
+            ),
+            (
+                4,
+                hex!(
+                    "E42B89E897F3CEFC336530AA9244AC19"
+                    "27A251949DB28D0891F09C038D332248"
+                    "FE9306A69798EFDCBA3EDB5E1EF792E1"
+                    "2F5E442BDF26A16CFDAF4758B1887460"
+                ),
+            ),
+            (
+                7,
+                hex!(
+                    "5CE6140135E8211AE91C7A9F7B3B52BE"
+                    "3A211D554A73AA92CA7660DBC81AB1FE"
+                    "1A8EB147908BC905822FA60094E25B84"
+                    "7A3E9BBC932A6C8C2E7A8106141C3807"
+                ),
+            ),
+        ],
+    },
+    TestVector {
+        name: "256-bit key, set 1, vector 0",
+        key: &hex!(
+            "80000000000000000000000000000000"
+            "00000000000000000000000000000000"
+        ),
+        iv: hex!("0000000000000000"),
+        blocks: &[
+            (
+                0,
+                hex!(
+                    "E3BE8FDD8BECA2E3EA8EF9475B29A6E7"
+                    "003951E1097A5C38D23B7A5FAD9F6844"
+                    "B22C97559E2723C7CBBD3FE4FC8D9A07"
+                    "44652A83E72A9C461876AF4D7EF1A117"
+                ),
+            ),
+            (
+                3,
+                hex!(
+                    "57BE81F47B17D9AE7C4FF15429A73E10"
+                    "ACF250ED3A90A93C711308A74C6216A9"
+                    "ED84CD126DA7F28E8ABF8BB63517E1CA"
+                    "98E712F4FB2E1A6AED9FDC73291FAA17"
+                ),
+            ),
+            (
+                4,
+                hex!(
+                    "958211C4BA2EBD5838C635EDB81F513A"
+                    "91A294E194F1C039AEEC657DCE40AA7E"
+                    "7C0AF57CACEFA40C9F14B71A4B3456A6"
+                    "3E162EC7D8D10B8FFB1810D71001B618"
+                ),
+            ),
+            (
+                7,
+                hex!(
+                    "696AFCFD0CDDCC83C7E77F11A649D79A"
+                    "CDC3354E9635FF137E929933A0BD6F53"
+                    "77EFA105A3A4266B7C0D089D08F1E855"
+                    "CC32B15B93784A36E56A76CC64BC8477"
+                ),
+            ),
+        ],
+    },
+    TestVector {
+        name: "256-bit key, set 6, vector 0",
+        key: &hex!(
+            "0053A6F94C9FF24598EB3E91E4378ADD"
+            "3083D6297CCF2275C81B6EC11467BA0D"
+        ),
+        iv: hex!("0D74DB42A91077DE"),
+        blocks: &[
+            (
+                0,
+                hex!(
+                    "F5FAD53F79F9DF58C4AEA0D0ED9A9601"
+                    "F278112CA7180D565B420A48019670EA"
+                    "F24CE493A86263F677B46ACE1924773D"
+                    "2BB25571E1AA8593758FC382B1280B71"
+                ),
+            ),
+            (
+                3,
+                hex!(
+                    "97E2D8F2B57393936D3DBFE613BCA6F8"
+                    "52A220624D20763DB8628E5A560005D0"
+                    "D1875B2C1CAA688D72B82D214732CD31"
+                    "D0C253B3051F58C6420B676288D2C254"
+                ),
+            ),
+            (
+                4,
+                hex!(
+                    "A5777721B1CD7701E8558401E97000D5"
+                    "6F842D657CA155C8FF589EE54D5B8138"
+                    "7FDC232A4BC4CA6DB57FA5159DC554E2"
+                    "E0A62C2706EC6BA4367B67BD0C3096A6"
+                ),
+            ),
+            (
+                7,
+                hex!(
+                    "C85BC2D2662DA0793FFCB57CCC71AFE9"
+                    "2D9E77E7AC75466E17AD0E28521183DB"
+                    "821432AA40F1FBA0296F5CBE7CF99290"
+                    "515501838E20B92E4D8D0266766D2124"
+                ),
+            ),
+        ],
+    },
+];
@@ -0,0 +1,52 @@
+//! Applying keystream to scattered buffers.
+
+use cipher::{StreamCipher, StreamCipherError};
+
+/// Extension trait applying one continuous keystream to several buffers.
+///
+/// Implemented for every [`StreamCipher`], e.g. [`Salsa20`][crate::Salsa20]
+/// and [`XSalsa20`][crate::XSalsa20].
+pub trait ApplyKeystreamVectored: StreamCipher {
+    /// Apply keystream to each of `bufs` in order, as if they were a single
+    /// contiguous buffer.
+    ///
+    /// The keystream continues across the buffer boundaries, also in the
+    /// middle of a block, so scattered data such as the buffers behind
+    /// [`IoSliceMut`](https://doc.rust-lang.org/std/io/struct.IoSliceMut.html)
+    /// does not have to be copied into one buffer first.
+    ///
+    /// Returns an error if the keystream would be exhausted. The buffers
+    /// before the one which does not fit are processed in that case, the
+    /// others and the keystream position after them are left untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use salsa20::{ApplyKeystreamVectored, Salsa20};
+    /// use salsa20::cipher::KeyIvInit;
+    ///
+    /// let mut cipher = Salsa20::new(&[0x42; 32].into(), &[0x24; 8].into());
+    /// let (mut a, mut b) = ([0x00, 0x01, 0x02], [0x03]);
+    /// cipher.try_apply_keystream_vectored(&mut [&mut a, &mut b]).unwrap();
+    /// assert_eq!((a, b), ([0x85, 0x84, 0x3c], [0xc5]));
+    /// ```
+    fn try_apply_keystream_vectored(
+        &mut self,
+        bufs: &mut [&mut [u8]],
+    ) -> Result<(), StreamCipherError> {
+        for buf in bufs {
+            self.try_apply_keystream(buf)?;
+        }
+        Ok(())
+    }
+
+    /// Apply keystream to each of `bufs` in order, see
+    /// [`try_apply_keystream_vectored`][Self::try_apply_keystream_vectored].
+    ///
+    /// # Panics
+    /// If the keystream would be exhausted.
+    fn apply_keystream_vectored(&mut self, bufs: &mut [&mut [u8]]) {
+        self.try_apply_keystream_vectored(bufs).unwrap();
+    }
+}
+
+impl<C: StreamCipher + ?Sized> ApplyKeystreamVectored for C {}
@@ -1,32 +1,80 @@
 //! XSalsa20 is an extended nonce variant of Salsa20
 
-use super::{Key, Nonce, SalsaCore, Unsigned, XNonce};
+use super::{Key, SalsaCore, Unsigned, XNonce};
 use cipher::{
     array::Array,
-    consts::{U10, U16, U24, U32, U4, U6, U64},
-    BlockSizeUser, IvSizeUser, KeyIvInit, KeySizeUser, StreamCipherClosure, StreamCipherCore,
-    StreamCipherCoreWrapper, StreamCipherSeekCore,
+    consts::{U10, U16, U24, U32, U64},
+    Block, BlockSizeUser, IvSizeUser, KeyInit, KeyIvInit, KeySizeUser, StreamCipherClosure,
+    StreamCipherCore, StreamCipherCoreWrapper, StreamCipherError, StreamCipherSeekCore,
 };
 
-use crate::backends::soft::quarter_round;
+use crate::backends::soft::double_round;
+use core::{fmt, marker::PhantomData};
 
+#[cfg(feature = "reduced-round")]
+use cipher::consts::{U4, U6};
 #[cfg(feature = "zeroize")]
-use cipher::zeroize::ZeroizeOnDrop;
+use cipher::zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// XSalsa20 is a Salsa20 variant with an extended 192-bit (24-byte) nonce.
 ///
 /// Based on the paper "Extending the Salsa20 Nonce":
 ///
 /// <https://cr.yp.to/snuffle/xsalsa-20081128.pdf>
+///
+/// # Seeking
+/// The keystream is the Salsa20 keystream of the derived subkey, so it uses
+/// the same 64-bit block counter: [`seek`][cipher::StreamCipherSeek::seek]
+/// accepts byte positions up to 2<sup>70</sup> (2<sup>64</sup> blocks of 64
+/// bytes), and applying keystream past the last block returns an error.
+///
+/// # Performance
+/// After the subkey is derived with [`hsalsa`], the keystream is generated
+/// by the inner [`SalsaCore`], which runs on the same SIMD backend as
+/// [`Salsa20`][crate::Salsa20]. Bulk throughput is therefore the same as for
+/// Salsa20, only key setup costs one HSalsa20 call more.
 pub type XSalsa20 = StreamCipherCoreWrapper<XSalsaCore<U10>>;
 /// XSalsa12 stream cipher (reduced-round variant of [`XSalsa20`] with 12 rounds)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
 pub type XSalsa12 = StreamCipherCoreWrapper<XSalsaCore<U6>>;
 /// XSalsa8 stream cipher (reduced-round variant of [`XSalsa20`] with 8 rounds)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
 pub type XSalsa8 = StreamCipherCoreWrapper<XSalsaCore<U4>>;
 
 /// The XSalsa core function.
 pub struct XSalsaCore<R: Unsigned>(SalsaCore<R, U32>);
 
+/// Cloning forks the cipher at its current block position, see the
+/// [`Clone`] implementation of [`SalsaCore`].
+impl<R: Unsigned> Clone for XSalsaCore<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        XSalsaCore(self.0.clone())
+    }
+}
+
+/// The key-derived state is never printed.
+impl<R: Unsigned> fmt::Debug for XSalsaCore<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XSalsaCore")
+            .field("double_rounds", &R::USIZE)
+            .field("key_size", &U32::USIZE)
+            .field("block_pos", &self.get_block_pos())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Not constant-time, see the [`PartialEq`] implementation of [`SalsaCore`].
+impl<R: Unsigned> PartialEq for XSalsaCore<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<R: Unsigned> Eq for XSalsaCore<R> {}
+
 impl<R: Unsigned> KeySizeUser for XSalsaCore<R> {
     type KeySize = U32;
 }
@@ -42,23 +90,136 @@ impl<R: Unsigned> BlockSizeUser for XSalsaCore<R> {
 impl<R: Unsigned> KeyIvInit for XSalsaCore<R> {
     #[inline]
     fn new(key: &Key<U32>, iv: &XNonce) -> Self {
-        let subkey = hsalsa::<R>(key, iv[..16].try_into().unwrap());
-        let mut padded_iv = Nonce::default();
-        padded_iv.copy_from_slice(&iv[16..]);
-        XSalsaCore(SalsaCore::new(&subkey, &padded_iv))
+        let (input, nonce) = Self::split_nonce(iv);
+        XSalsaCore(SalsaCore::new(
+            &hsalsa::<R>(key, input.into()),
+            nonce.into(),
+        ))
+    }
+}
+
+impl<R: Unsigned> XSalsaCore<R> {
+    /// Split `nonce` into the 16 bytes passed to [`hsalsa`] and the 8 bytes
+    /// passed to the inner Salsa core, i.e. its first 16 and last 8 bytes.
+    ///
+    /// ```
+    /// use salsa20::{cipher::consts::U10, XSalsaCore};
+    ///
+    /// let nonce = core::array::from_fn::<u8, 24, _>(|i| i as u8).into();
+    /// let (input, inner) = XSalsaCore::<U10>::split_nonce(&nonce);
+    /// assert_eq!(input[15], 15);
+    /// assert_eq!(inner, &[16, 17, 18, 19, 20, 21, 22, 23]);
+    /// ```
+    pub fn split_nonce(nonce: &XNonce) -> (&[u8; 16], &[u8; 8]) {
+        let (input, inner) = nonce.split_ref::<U16>();
+        (&input.0, &inner.0)
+    }
+
+    /// Derive the subkey which [`KeyIvInit::new`] passes to the inner Salsa
+    /// core together with the last 8 bytes of `nonce`.
+    ///
+    /// This is [`hsalsa`] of `key` and the first 16 bytes of `nonce`, i.e. the
+    /// first step of the libsodium and NaCl `crypto_stream_xsalsa20`
+    /// functions, exposed to verify the derivation independently.
+    pub fn subkey(key: &Key<U32>, nonce: &XNonce) -> [u8; 32] {
+        hsalsa::<R>(key, Self::split_nonce(nonce).0.into()).into()
+    }
+
+    /// Return the inner Salsa core, which generates the keystream from the
+    /// [`subkey`][Self::subkey] and the last 8 bytes of the nonce.
+    ///
+    /// The inner core continues at the current block position, so its
+    /// keystream is the rest of the XSalsa keystream. It can be seeked and
+    /// inspected like any other [`SalsaCore`].
+    ///
+    /// ```
+    /// use salsa20::{SalsaCore, XSalsaCore};
+    /// use salsa20::cipher::{consts::{U10, U32}, KeyIvInit};
+    ///
+    /// let (key, nonce) = ([0x42; 32].into(), [0x24; 24].into());
+    /// let inner = XSalsaCore::<U10>::new(&key, &nonce).into_inner();
+    /// let subkey = XSalsaCore::<U10>::subkey(&key, &nonce);
+    /// assert_eq!(inner, SalsaCore::<U10, U32>::new(&subkey.into(), &[0x24; 8].into()));
+    /// ```
+    pub fn into_inner(self) -> SalsaCore<R, U32> {
+        self.0
+    }
+
+    /// Zeroize the state without waiting for the core to be dropped, see
+    /// [`SalsaCore::zeroize`].
+    #[cfg(feature = "zeroize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+    #[inline]
+    pub fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+
+    /// Get the audit of the keystream block positions generated so far, see
+    /// [`SalsaCore::consumed_positions`].
+    #[cfg(all(feature = "paranoid", debug_assertions))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "paranoid", debug_assertions))))]
+    pub fn consumed_positions(&self) -> crate::ConsumedPositions {
+        self.0.consumed_positions()
+    }
+
+    /// Get the number of keystream blocks left before the 64-bit block
+    /// counter would wrap around, see [`SalsaCore::remaining_blocks`].
+    #[inline]
+    pub fn remaining_blocks(&self) -> u64 {
+        self.0.remaining_blocks()
+    }
+
+    /// Get the number of keystream bytes left before the 64-bit block
+    /// counter would wrap around, see [`SalsaCore::remaining_bytes`].
+    #[inline]
+    pub fn remaining_bytes(&self) -> Option<u64> {
+        self.0.remaining_bytes()
+    }
+
+    /// Seek to the keystream block `pos`, failing if no block would be left
+    /// to generate, see [`SalsaCore::try_set_block_pos`].
+    #[inline]
+    pub fn try_set_block_pos(&mut self, pos: u64) -> Result<(), StreamCipherError> {
+        self.0.try_set_block_pos(pos)
+    }
+
+    /// Number of blocks the active backend generates per parallel batch, see
+    /// [`SalsaCore::par_blocks`].
+    #[inline]
+    pub fn par_blocks(&self) -> usize {
+        self.0.par_blocks()
+    }
+
+    /// Apply keystream to `buf`, starting at the current block, on the
+    /// [`rayon`] thread pool.
+    ///
+    /// See [`SalsaCore::par_apply_keystream`], in particular the block
+    /// position is **not** advanced.
+    ///
+    /// # Panics
+    /// If processing `buf` would overflow the 64-bit block counter.
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn par_apply_keystream(&self, buf: &mut [u8]) {
+        self.0.par_apply_keystream(buf);
     }
 }
 
 impl<R: Unsigned> StreamCipherCore for XSalsaCore<R> {
     #[inline(always)]
     fn remaining_blocks(&self) -> Option<usize> {
-        self.0.remaining_blocks()
+        StreamCipherCore::remaining_blocks(&self.0)
     }
 
     #[inline(always)]
     fn process_with_backend(&mut self, f: impl StreamCipherClosure<BlockSize = Self::BlockSize>) {
         self.0.process_with_backend(f);
     }
+
+    #[inline(always)]
+    fn write_keystream_block(&mut self, block: &mut Block<Self>) {
+        self.0.write_keystream_block(block);
+    }
 }
 
 impl<R: Unsigned> StreamCipherSeekCore for XSalsaCore<R> {
@@ -79,6 +240,126 @@ impl<R: Unsigned> StreamCipherSeekCore for XSalsaCore<R> {
 #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
 impl<R: Unsigned> ZeroizeOnDrop for XSalsaCore<R> {}
 
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned> Zeroize for XSalsaCore<R> {
+    fn zeroize(&mut self) {
+        XSalsaCore::zeroize(self);
+    }
+}
+
+/// HSalsa function with `R` double rounds, usable as a type parameter of
+/// custom XSalsa-like constructions.
+///
+/// See [`hsalsa`] for details.
+pub struct HSalsa<R: Unsigned>(PhantomData<R>);
+
+impl<R: Unsigned> HSalsa<R> {
+    /// Derive a 256-bit subkey from `key` and the 128-bit `input`.
+    #[inline]
+    pub fn hash(key: &Key<U32>, input: &Array<u8, U16>) -> Array<u8, U32> {
+        hsalsa::<R>(key, input)
+    }
+
+    /// Derive a subkey from `key` for each of the `inputs`, see [`hsalsa_many`].
+    #[inline]
+    pub fn hash_many(key: &Key<U32>, inputs: &[[u8; 16]], out: &mut [[u8; 32]]) {
+        hsalsa_many::<R>(key, inputs, out)
+    }
+}
+
+/// HSalsa8 function (reduced-round variant of [`HSalsa20`] with 8 rounds)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type HSalsa8 = HSalsa<U4>;
+/// HSalsa12 function (reduced-round variant of [`HSalsa20`] with 12 rounds)
+#[cfg(feature = "reduced-round")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reduced-round")))]
+pub type HSalsa12 = HSalsa<U6>;
+/// HSalsa20 function used to derive [`XSalsa20`] subkeys
+pub type HSalsa20 = HSalsa<U10>;
+
+/// Key derivation function with a fixed key, based on [`hsalsa`] with `R`
+/// double rounds.
+///
+/// Implements [`KeyInit`], so it can be constructed like the ciphers of this
+/// crate, e.g. in generic key-derivation code. [`derive`][Self::derive] maps
+/// each 128-bit input to a 256-bit output key.
+///
+/// # Security
+/// HSalsa20 is a pseudo-random function only for a uniformly random key: it
+/// is not a hash of the key, and its output does not authenticate anything.
+/// Distinct inputs yield independent outputs, so each derived key should be
+/// bound to a distinct input such as a nonce or a counter.
+///
+/// # Example
+/// ```
+/// use salsa20::HSalsa20Kdf;
+/// use salsa20::cipher::KeyInit;
+///
+/// let kdf = HSalsa20Kdf::new(&[0x42; 32].into());
+/// let key_a = kdf.derive(&[0; 16]);
+/// let key_b = kdf.derive(&[1; 16]);
+/// assert_ne!(key_a, key_b);
+/// ```
+pub struct HSalsaKdf<R: Unsigned> {
+    key: Key<U32>,
+    rounds: PhantomData<R>,
+}
+
+/// HSalsa20-based key derivation function, see [`HSalsaKdf`]
+pub type HSalsa20Kdf = HSalsaKdf<U10>;
+
+impl<R: Unsigned> HSalsaKdf<R> {
+    /// Derive the 256-bit output key for `input`.
+    #[inline]
+    pub fn derive(&self, input: &[u8; 16]) -> Array<u8, U32> {
+        hsalsa::<R>(&self.key, input.into())
+    }
+}
+
+impl<R: Unsigned> KeySizeUser for HSalsaKdf<R> {
+    type KeySize = U32;
+}
+
+impl<R: Unsigned> KeyInit for HSalsaKdf<R> {
+    #[inline]
+    fn new(key: &Key<U32>) -> Self {
+        Self {
+            key: *key,
+            rounds: PhantomData,
+        }
+    }
+}
+
+impl<R: Unsigned> Clone for HSalsaKdf<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new(&self.key)
+    }
+}
+
+/// The key is never printed.
+impl<R: Unsigned> fmt::Debug for HSalsaKdf<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HSalsaKdf")
+            .field("double_rounds", &R::USIZE)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned> Drop for HSalsaKdf<R> {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Unsigned> ZeroizeOnDrop for HSalsaKdf<R> {}
+
 /// The HSalsa20 function defined in the paper "Extending the Salsa20 nonce"
 ///
 /// <https://cr.yp.to/snuffle/xsalsa-20110204.pdf>
@@ -89,53 +370,268 @@ impl<R: Unsigned> ZeroizeOnDrop for XSalsaCore<R> {}
 /// - Key (`u32` x 8)
 /// - Nonce (`u32` x 4)
 ///
-/// It produces 256-bits of output suitable for use as a Salsa20 key
+/// It produces 256-bits of output suitable for use as a Salsa20 key.
+///
+/// `R` is the number of double rounds, e.g. `U10` for HSalsa20 and `U4`
+/// or `U6` for the reduced-round HSalsa8 and HSalsa12.
+///
+/// The subkey is returned as [`Array`], which can be passed to the
+/// constructors of other ciphers as is. Use `.into()` where a `[u8; 32]` is
+/// needed, as returned by [`XSalsaCore::subkey`].
+///
+/// # Example
+/// ```
+/// use salsa20::{hsalsa, Salsa20};
+/// use salsa20::cipher::{consts::U10, KeyIvInit};
+///
+/// let subkey = hsalsa::<U10>(&[0x42; 32].into(), &[0x24; 16].into());
+/// let cipher = Salsa20::new(&subkey, &[0; 8].into());
+/// let bytes: [u8; 32] = subkey.into();
+/// ```
 pub fn hsalsa<R: Unsigned>(key: &Key<U32>, input: &Array<u8, U16>) -> Array<u8, U32> {
-    #[inline(always)]
-    fn to_u32(chunk: &[u8]) -> u32 {
-        u32::from_le_bytes(chunk.try_into().unwrap())
-    }
-
-    let mut state = [0u32; 16];
     let constants = crate::constants(key.len());
-    state[0] = constants[0];
-    state[1..5]
-        .iter_mut()
-        .zip(key[0..16].chunks_exact(4))
-        .for_each(|(v, chunk)| *v = to_u32(chunk));
-    state[5] = constants[1];
-    state[6..10]
-        .iter_mut()
-        .zip(input.chunks_exact(4))
-        .for_each(|(v, chunk)| *v = to_u32(chunk));
-    state[10] = constants[2];
-    state[11..15]
-        .iter_mut()
-        .zip(key[16..].chunks_exact(4))
-        .for_each(|(v, chunk)| *v = to_u32(chunk));
-    state[15] = constants[3];
-
-    // 20 rounds consisting of 10 column rounds and 10 diagonal rounds
-    for _ in 0..R::USIZE {
-        // column rounds
-        quarter_round(0, 4, 8, 12, &mut state);
-        quarter_round(5, 9, 13, 1, &mut state);
-        quarter_round(10, 14, 2, 6, &mut state);
-        quarter_round(15, 3, 7, 11, &mut state);
+    let (key, input) = (&key.0, &input.0);
+    let mut state = [
+        constants[0],
+        word(key, 0),
+        word(key, 4),
+        word(key, 8),
+        word(key, 12),
+        constants[1],
+        word(input, 0),
+        word(input, 4),
+        word(input, 8),
+        word(input, 12),
+        constants[2],
+        word(key, 16),
+        word(key, 20),
+        word(key, 24),
+        word(key, 28),
+        constants[3],
+    ];
 
-        // diagonal rounds
-        quarter_round(0, 1, 2, 3, &mut state);
-        quarter_round(5, 6, 7, 4, &mut state);
-        quarter_round(10, 11, 8, 9, &mut state);
-        quarter_round(15, 12, 13, 14, &mut state);
+    // 2 * R rounds consisting of R column rounds and R diagonal rounds.
+    //
+    // These stay scalar even where the stream cipher uses SSE2: with a single
+    // block the SSE2 rounds are one long dependency chain, while the CPU runs
+    // the four independent scalar quarter-rounds in parallel. The SSE2 version
+    // benchmarked about 15% slower (see the `XSalsa20-setup` benchmark).
+    for _ in 0..R::USIZE {
+        double_round(&mut state);
     }
 
     let mut output = Array::default();
-    let key_idx: [usize; 8] = [0, 5, 10, 15, 6, 7, 8, 9];
 
     for (i, chunk) in output.chunks_exact_mut(4).enumerate() {
-        chunk.copy_from_slice(&state[key_idx[i]].to_le_bytes());
+        chunk.copy_from_slice(&state[OUTPUT_WORDS[i]].to_le_bytes());
     }
+    #[cfg(feature = "zeroize")]
+    state.zeroize();
 
     output
 }
+
+/// Load the little-endian word at the fixed offset `i` of `bytes`.
+///
+/// Indexing an array with constant offsets lets the compiler drop the bounds
+/// checks, which a `chunks_exact(4)` loop with `try_into().unwrap()` keeps.
+#[inline(always)]
+fn word<const N: usize>(bytes: &[u8; N], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+/// State words which form the HSalsa output, in output order.
+const OUTPUT_WORDS: [usize; 8] = [0, 5, 10, 15, 6, 7, 8, 9];
+
+/// Derive a subkey from `key` for each of the 128-bit `inputs` with
+/// [`hsalsa`], writing them to `out`.
+///
+/// This is equivalent to calling [`hsalsa`] for each input, but on x86(-64)
+/// CPUs with SSE2 four subkeys are derived at once, which cuts the key setup
+/// cost of servers establishing many XSalsa20 sessions with the same key.
+///
+/// # Panics
+/// If `inputs` and `out` have different lengths.
+///
+/// # Example
+/// ```
+/// use salsa20::{hsalsa, hsalsa_many, cipher::consts::U10};
+///
+/// let key = [0x42; 32].into();
+/// let inputs = [[0u8; 16], [1; 16], [2; 16], [3; 16], [4; 16]];
+/// let mut out = [[0u8; 32]; 5];
+/// hsalsa_many::<U10>(&key, &inputs, &mut out);
+///
+/// for (input, out) in inputs.iter().zip(out.iter()) {
+///     assert_eq!(out[..], hsalsa::<U10>(&key, &(*input).into())[..]);
+/// }
+/// ```
+pub fn hsalsa_many<R: Unsigned>(key: &Key<U32>, inputs: &[[u8; 16]], out: &mut [[u8; 32]]) {
+    assert_eq!(
+        inputs.len(),
+        out.len(),
+        "inputs and outputs differ in length"
+    );
+
+    #[allow(unused_mut)]
+    let mut done = 0;
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(salsa20_force_soft)
+    ))]
+    if has_sse2() {
+        done = hsalsa_x4::<R>(key, inputs, out);
+    }
+
+    for (input, out) in inputs[done..].iter().zip(out[done..].iter_mut()) {
+        out.copy_from_slice(&hsalsa::<R>(key, &(*input).into()));
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(salsa20_force_soft)
+))]
+#[inline(always)]
+fn has_sse2() -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(any(salsa20_force_sse2, target_feature = "avx512f"))] {
+            true
+        } else {
+            crate::cpuid::has_sse2()
+        }
+    }
+}
+
+/// Derive subkeys four at a time with the SSE2 backend, returning the number
+/// of subkeys derived. The remaining `inputs.len() % 4` subkeys are left to
+/// the caller.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(salsa20_force_soft)
+))]
+fn hsalsa_x4<R: Unsigned>(key: &Key<U32>, inputs: &[[u8; 16]], out: &mut [[u8; 32]]) -> usize {
+    let constants = crate::constants(key.len());
+    let key = &key.0;
+    let mut state = [[0u32; 4]; 16];
+    state[0] = [constants[0]; 4];
+    state[5] = [constants[1]; 4];
+    state[10] = [constants[2]; 4];
+    state[15] = [constants[3]; 4];
+    for i in 0..4 {
+        // key words go to state words 1 to 4 and 11 to 14
+        state[1 + i] = [word(key, 4 * i); 4];
+        state[11 + i] = [word(key, 16 + 4 * i); 4];
+    }
+
+    for (inputs, outs) in inputs.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+        let mut x = state;
+        for (lane, input) in inputs.iter().enumerate() {
+            for i in 0..4 {
+                x[6 + i][lane] = word(input, 4 * i);
+            }
+        }
+
+        unsafe {
+            crate::backends::sse2::hsalsa_rounds_x4::<R>(&mut x);
+        }
+
+        for (lane, out) in outs.iter_mut().enumerate() {
+            for (chunk, &i) in out.chunks_exact_mut(4).zip(OUTPUT_WORDS.iter()) {
+                chunk.copy_from_slice(&x[i][lane].to_le_bytes());
+            }
+        }
+        #[cfg(feature = "zeroize")]
+        x.zeroize();
+    }
+    #[cfg(feature = "zeroize")]
+    state.zeroize();
+    inputs.len() - inputs.len() % 4
+}
+
+#[cfg(test)]
+mod hsalsa_tests {
+    use super::*;
+    use cipher::consts::{U4, U6};
+    use hex_literal::hex;
+
+    const KEY: [u8; 32] = hex!(
+        "1b27556473e985d462cd51197a9a46c7"
+        "6009549eac6474f206c4ee0844f68389"
+    );
+
+    const INPUT: [u8; 16] = hex!("69696ee955b62b73cd62bda875fc73d6");
+
+    /// Test vectors from NaCl `tests/core1.c` and `tests/core2.c`
+    #[test]
+    fn hsalsa20() {
+        const SHARED: [u8; 32] = hex!(
+            "4a5d9d5ba4ce2de1728e3bf480350f25"
+            "e07e21c947d19e3376f09b3c1e161742"
+        );
+
+        const OUTPUT: [u8; 32] = hex!(
+            "dc908dda0b9344a953629b7338207788"
+            "80f3ceb421bb61b91cbd4c3e66256ce4"
+        );
+
+        let actual = hsalsa::<U10>(SHARED.as_ref(), &Default::default());
+        assert_eq!(actual.as_slice(), &KEY);
+
+        let actual = HSalsa20::hash(KEY.as_ref(), INPUT.as_ref());
+        assert_eq!(actual.as_slice(), &OUTPUT);
+    }
+
+    #[test]
+    fn hsalsa8() {
+        const OUTPUT: [u8; 32] = hex!(
+            "0cc7a200c2fe665426a3a693c700882f"
+            "e14b1990ac9e0b88f7e518c88e97ebcc"
+        );
+
+        let actual = HSalsa::<U4>::hash(KEY.as_ref(), INPUT.as_ref());
+        assert_eq!(actual.as_slice(), &OUTPUT);
+    }
+
+    #[test]
+    fn hsalsa12() {
+        const OUTPUT: [u8; 32] = hex!(
+            "090f983c6ff15ee6b25a3c88f3a66594"
+            "c78e0873ebdf4a0d3aed2e0951062f37"
+        );
+
+        let actual = HSalsa::<U6>::hash(KEY.as_ref(), INPUT.as_ref());
+        assert_eq!(actual.as_slice(), &OUTPUT);
+    }
+    #[test]
+    fn hsalsa_many_matches_hsalsa() {
+        let mut inputs = [[0u8; 16]; 11];
+        for (i, input) in inputs.iter_mut().enumerate() {
+            *input = INPUT;
+            input[0] ^= i as u8;
+        }
+
+        // every remainder of the 4-way batches
+        for len in 0..=inputs.len() {
+            let mut out = [[0u8; 32]; 11];
+            HSalsa20::hash_many(KEY.as_ref(), &inputs[..len], &mut out[..len]);
+            for (input, out) in inputs[..len].iter().zip(out.iter()) {
+                let expected = hsalsa::<U10>(KEY.as_ref(), input.as_ref());
+                assert_eq!(out, expected.as_slice());
+            }
+            assert!(out[len..].iter().all(|out| *out == [0; 32]));
+        }
+
+        let mut out = [[0u8; 32]; 4];
+        hsalsa_many::<U4>(KEY.as_ref(), &[INPUT; 4], &mut out);
+        assert!(out
+            .iter()
+            .all(|out| *out == HSalsa::<U4>::hash(KEY.as_ref(), INPUT.as_ref())[..]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn hsalsa_many_length_mismatch() {
+        hsalsa_many::<U10>(KEY.as_ref(), &[INPUT; 2], &mut [[0; 32]; 3]);
+    }
+}
@@ -1,12 +1,79 @@
 //! Salsa20 tests
 
-use cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+#![allow(unexpected_cfgs)]
+
+use cipher::{
+    consts::{U16, U32, U8},
+    KeyIvInit, StreamCipher, StreamCipherSeek,
+};
 use hex_literal::hex;
 use salsa20::Salsa20;
 use salsa20::XSalsa20;
+#[cfg(feature = "reduced-round")]
+use salsa20::{Salsa12, Salsa8};
+
+cipher::stream_cipher_seek_test!(salsa20_seek, Seek<Salsa20>);
+cipher::stream_cipher_seek_test!(xsalsa20_seek, Seek<XSalsa20>);
+
+#[cfg(not(all(feature = "paranoid", debug_assertions)))]
+type Seek<C> = C;
+
+/// The second half of the seek tests seeks back to the start after every
+/// kilobyte and applies the keystream again with the same instance, which
+/// the `paranoid` feature rejects as reuse.
+#[cfg(all(feature = "paranoid", debug_assertions))]
+type Seek<C> = FreshOnSeek<C>;
+
+/// Cipher which continues with a fresh instance of `C` after every seek.
+#[cfg(all(feature = "paranoid", debug_assertions))]
+struct FreshOnSeek<C: KeyIvInit> {
+    cipher: C,
+    key: cipher::Key<C>,
+    iv: cipher::Iv<C>,
+}
+
+#[cfg(all(feature = "paranoid", debug_assertions))]
+impl<C: KeyIvInit> cipher::KeySizeUser for FreshOnSeek<C> {
+    type KeySize = C::KeySize;
+}
+
+#[cfg(all(feature = "paranoid", debug_assertions))]
+impl<C: KeyIvInit> cipher::IvSizeUser for FreshOnSeek<C> {
+    type IvSize = C::IvSize;
+}
+
+#[cfg(all(feature = "paranoid", debug_assertions))]
+impl<C: KeyIvInit> KeyIvInit for FreshOnSeek<C> {
+    fn new(key: &cipher::Key<C>, iv: &cipher::Iv<C>) -> Self {
+        Self {
+            cipher: C::new(key, iv),
+            key: key.clone(),
+            iv: iv.clone(),
+        }
+    }
+}
+
+#[cfg(all(feature = "paranoid", debug_assertions))]
+impl<C: KeyIvInit + StreamCipher> StreamCipher for FreshOnSeek<C> {
+    fn try_apply_keystream_inout(
+        &mut self,
+        buf: cipher::InOutBuf<'_, '_, u8>,
+    ) -> Result<(), cipher::StreamCipherError> {
+        self.cipher.try_apply_keystream_inout(buf)
+    }
+}
+
+#[cfg(all(feature = "paranoid", debug_assertions))]
+impl<C: KeyIvInit + StreamCipherSeek> StreamCipherSeek for FreshOnSeek<C> {
+    fn try_current_pos<T: cipher::SeekNum>(&self) -> Result<T, cipher::OverflowError> {
+        self.cipher.try_current_pos()
+    }
 
-cipher::stream_cipher_seek_test!(salsa20_seek, Salsa20);
-cipher::stream_cipher_seek_test!(xsalsa20_seek, XSalsa20);
+    fn try_seek<T: cipher::SeekNum>(&mut self, pos: T) -> Result<(), cipher::StreamCipherError> {
+        self.cipher = C::new(&self.key, &self.iv);
+        self.cipher.try_seek(pos)
+    }
+}
 
 const KEY_BYTES: usize = 32;
 
@@ -78,6 +145,59 @@ const EXPECTED_LONG: [u8; 256] = hex!(
     "cbe6a7161e8653ce9391e1e6710ed4f1"
 );
 
+#[cfg(feature = "reduced-round")]
+const EXPECTED_LONG_SALSA8: [u8; 128] = hex!(
+    "5096430623153cd828acc9ac1bd84440"
+    "8466f2581d1a653fe55c67ebfd001df0"
+    "0b073813c3e7babbb8c30757f00e9d5a"
+    "812a69233c558ddbfa00e4f40967b237"
+    "36bac56e784abba61601d0f82281328d"
+    "aba8d5b27fa27b253e0d6fadcd4dacc3"
+    "4cef4e813861f056962a6c9a67ce48b1"
+    "1b542d4511de6215ed980e96b9b0dfef"
+);
+
+/// First block of Salsa20/16, generated with an independent reference
+/// implementation.
+const EXPECTED_LONG_SALSA16: [u8; 64] = hex!(
+    "4e5f133e8daa41e558e07d03b0ed181a"
+    "10371d37bff7217462833a78a11b2d5c"
+    "086eb4497a3a3a15fa40bab8319e89a8"
+    "c2e584b0ad47caf3e709f8c7bcb2b002"
+);
+
+#[cfg(feature = "reduced-round")]
+const EXPECTED_LONG_SALSA12: [u8; 128] = hex!(
+    "a5a89b1a205e5dfaceac52f267d1370c"
+    "9ab9360aac690910d5487b5f8f7cf6c0"
+    "c6b9f5edbf3d7c1d9a94732339a6d36c"
+    "712d5e91bcb752b643c6f2817c241871"
+    "3c41ea2a202d1f0129c97fc329d3db39"
+    "012b49646a0578f0e38383055f0648b8"
+    "add2cdf39c7e0846dd89fe6cd42652e3"
+    "5aca634e8b51dff86b1402b3c8a31647"
+);
+
+/// Keystream for `KEY_LONG`/`IV_LONG` starting two blocks before the 2^32 block boundary
+const EXPECTED_LONG_CARRY: [u8; 256] = hex!(
+    "0e27db475795c158107b413c2ed877bc"
+    "65cd1da2ffdf01ed8a5a34c741d11125"
+    "f41558cd95c02be7bf2204c3abd2daa7"
+    "ce5b0c038128e35156721a25a4612404"
+    "fe6424fbcec4ecab5beea6dfdce9b233"
+    "24f4f7caae6110e63289411aefb164a1"
+    "615e0c71fe2b849bd3c8df0da0d4402d"
+    "07eea4d713928976bfa963c3f752658b"
+    "b21234c0a9e7fcbf0b4e505fa214d640"
+    "70d7985fce6c296691d29b7e980afde8"
+    "fa702596923db652e790c58c4137953c"
+    "14ad888c4112d7eaa7b64bce0c910cce"
+    "4748a8ccd2f62f434ecd7d752eceb1c8"
+    "876f9069adc6ec6e9c0894986b2f8ae9"
+    "eaac4a7e3a1d3d1098399ce1f454b904"
+    "7e76eb431bfb1a8c7e449af6b925ee1e"
+);
+
 const EXPECTED_XSALSA20_ZEROS: [u8; 64] = hex!(
     "4848297feb1fb52fb66d81609bd547fa"
     "bcbe7026edc8b5e5e449d088bfa69c08"
@@ -93,6 +213,59 @@ const SIXTEEN_BYTE_KEY_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3120_646e, 0x7962_2
 const NINETY_NINE_BYTE_KEY_CONSTANTS: [u32; 4] =
     [0x6170_7865, 0x3920_646e, 0x7962_2d39, 0x6b20_6574];
 
+/// Portable scalar Salsa20/20 implementation used to check the SIMD backends
+fn soft_keystream(
+    key: &[u8; KEY_BYTES],
+    iv: &[u8; IV_BYTES],
+    double_rounds: usize,
+    buf: &mut [u8],
+) {
+    fn quarter_round(a: usize, b: usize, c: usize, d: usize, x: &mut [u32; 16]) {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    }
+
+    let word = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    let c = THIRTY_TWO_BYTE_KEY_CONSTANTS;
+    for (ctr, chunk) in buf.chunks_mut(64).enumerate() {
+        let ctr = ctr as u64;
+        let state = [
+            c[0],
+            word(&key[0..4]),
+            word(&key[4..8]),
+            word(&key[8..12]),
+            word(&key[12..16]),
+            c[1],
+            word(&iv[0..4]),
+            word(&iv[4..8]),
+            ctr as u32,
+            (ctr >> 32) as u32,
+            c[2],
+            word(&key[16..20]),
+            word(&key[20..24]),
+            word(&key[24..28]),
+            word(&key[28..32]),
+            c[3],
+        ];
+        let mut x = state;
+        for _ in 0..double_rounds {
+            quarter_round(0, 4, 8, 12, &mut x);
+            quarter_round(5, 9, 13, 1, &mut x);
+            quarter_round(10, 14, 2, 6, &mut x);
+            quarter_round(15, 3, 7, 11, &mut x);
+            quarter_round(0, 1, 2, 3, &mut x);
+            quarter_round(5, 6, 7, 4, &mut x);
+            quarter_round(10, 11, 8, 9, &mut x);
+            quarter_round(15, 12, 13, 14, &mut x);
+        }
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b ^= x[i / 4].wrapping_add(state[i / 4]).to_le_bytes()[i % 4];
+        }
+    }
+}
+
 #[test]
 fn key_constants() {
     assert_eq!(
@@ -159,6 +332,117 @@ fn key_constants() {
     );
 }
 
+/// Salsa20/20 with a 128-bit key
+type Salsa20K16 = cipher::StreamCipherCoreWrapper<salsa20::SalsaCore<cipher::consts::U10, U16>>;
+
+const KEY1_128: [u8; 16] = hex!("80000000000000000000000000000000");
+
+const KEY_SET6_128: [u8; 16] = hex!("0053A6F94C9FF24598EB3E91E4378ADD");
+
+const IV_SET6: [u8; IV_BYTES] = hex!("0D74DB42A91077DE");
+
+/// Test vectors from the eSTREAM `verified.test-vectors` file, Set 1, vector# 0
+const EXPECTED_KEY1_128_IV0: [(usize, [u8; 64]); 2] = [
+    (
+        0,
+        hex!(
+            "4DFA5E481DA23EA09A31022050859936"
+            "DA52FCEE218005164F267CB65F5CFD7F"
+            "2B4F97E0FF16924A52DF269515110A07"
+            "F9E460BC65EF95DA58F740B7D1DBB0AA"
+        ),
+    ),
+    (
+        448,
+        hex!(
+            "B375703739DACED4DD4059FD71C3C47F"
+            "C2F9939670FAD4A46066ADCC6A564578"
+            "3308B90FFB72BE04A6B147CBE38CC0C3"
+            "B9267C296A92A7C69873F9F263BE9703"
+        ),
+    ),
+];
+
+/// Test vectors from the eSTREAM `verified.test-vectors` file, Set 6, vector# 0
+const EXPECTED_SET6_128: [(usize, [u8; 64]); 2] = [
+    (
+        0,
+        hex!(
+            "05E1E7BEB697D999656BF37C1B978806"
+            "735D0B903A6007BD329927EFBE1B0E2A"
+            "8137C1AE291493AA83A821755BEE0B06"
+            "CD14855A67E46703EBF8F3114B584CBA"
+        ),
+    ),
+    (
+        65472,
+        hex!(
+            "1A70A37B1C9CA11CD3BF988D3EE4612D"
+            "15F1A08D683FCCC6558ECF2089388B8E"
+            "555E7619BF82EE71348F4F8D0D2AE464"
+            "339D66BFC3A003BF229C0FC0AB6AE1C6"
+        ),
+    ),
+];
+
+#[test]
+fn size_constants() {
+    use cipher::{
+        consts::{U10, U16},
+        typenum::Unsigned,
+        BlockSizeUser, IvSizeUser, KeySizeUser,
+    };
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    type Core = SalsaCore<U10, U32>;
+    assert_eq!(salsa20::SALSA20_ROUNDS, 2 * U10::USIZE);
+    assert_eq!(
+        salsa20::BLOCK_SIZE,
+        <Core as BlockSizeUser>::BlockSize::USIZE
+    );
+    assert_eq!(salsa20::KEY_SIZE, <Core as KeySizeUser>::KeySize::USIZE);
+    assert_eq!(
+        salsa20::KEY_SIZE_128,
+        <SalsaCore<U10, U16> as KeySizeUser>::KeySize::USIZE
+    );
+    assert_eq!(salsa20::NONCE_SIZE, <Core as IvSizeUser>::IvSize::USIZE);
+    assert_eq!(
+        salsa20::XNONCE_SIZE,
+        <XSalsaCore<U10> as IvSizeUser>::IvSize::USIZE
+    );
+    assert_eq!(salsa20::KEY_SIZE, KEY_BYTES);
+    assert_eq!(salsa20::NONCE_SIZE, IV_BYTES);
+}
+
+#[test]
+fn salsa20_128bit_key() {
+    let constants: Vec<u8> = salsa20::constants(16)
+        .iter()
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    assert_eq!(constants, b"expand 16-byte k");
+
+    for (key, iv, expected) in [
+        (KEY1_128, IV0, EXPECTED_KEY1_128_IV0),
+        (KEY_SET6_128, IV_SET6, EXPECTED_SET6_128),
+    ] {
+        for (pos, block) in expected {
+            // seek directly to the block
+            let mut cipher = Salsa20K16::new(&key.into(), &iv.into());
+            let mut buf = [0u8; 64];
+            cipher.seek(pos);
+            cipher.apply_keystream(&mut buf);
+            assert_eq!(buf, block, "mismatch at {pos}");
+
+            // generate the whole stream up to the block
+            let mut cipher = Salsa20K16::new(&key.into(), &iv.into());
+            let mut buf = vec![0u8; pos + 64];
+            cipher.apply_keystream(&mut buf);
+            assert_eq!(buf[pos..], block, "mismatch at {pos}");
+        }
+    }
+}
+
 #[test]
 fn salsa20_key1_iv0() {
     let mut cipher = Salsa20::new(&KEY1.into(), &IV0.into());
@@ -207,6 +491,2581 @@ fn salsa20_long() {
     }
 }
 
+#[test]
+#[cfg(feature = "reduced-round")]
+fn salsa8_long() {
+    let mut cipher = Salsa8::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0; 128];
+
+    // two calls to check that the block counter is carried over
+    cipher.apply_keystream(&mut buf[..64]);
+    cipher.apply_keystream(&mut buf[64..]);
+    assert_eq!(buf, EXPECTED_LONG_SALSA8);
+}
+
+#[test]
+#[cfg(feature = "reduced-round")]
+fn salsa12_long() {
+    let mut cipher = Salsa12::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0; 128];
+
+    cipher.apply_keystream(&mut buf[..64]);
+    cipher.apply_keystream(&mut buf[64..]);
+    assert_eq!(buf, EXPECTED_LONG_SALSA12);
+}
+
+#[test]
+fn salsa20_counter_carry() {
+    let start = (1u64 << 32) - 2;
+
+    // all four blocks in one call, crossing the boundary inside a parallel group
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0; 256];
+    cipher.seek(start * 64);
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+    assert_eq!(cipher.current_pos::<u64>(), (start + 4) * 64);
+
+    // one block per call
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0; 256];
+    cipher.seek(start * 64);
+    for chunk in buf.chunks_mut(64) {
+        cipher.apply_keystream(chunk);
+    }
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+}
+
+#[test]
+fn salsa20_counter32() {
+    use cipher::{
+        consts::{U10, U4},
+        StreamCipherCoreWrapper, StreamCipherSeekCore,
+    };
+    use salsa20::SalsaCore;
+
+    type Core32 = SalsaCore<U10, U32, U4>;
+    type Salsa20Ctr32 = StreamCipherCoreWrapper<Core32>;
+
+    // same keystream as Salsa20 below the 32-bit boundary
+    let mut cipher = Salsa20Ctr32::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0; 128];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG[..128]);
+
+    // the keystream ends before the counter would carry
+    let start = u32::MAX - 1;
+    let mut cipher = Salsa20Ctr32::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.seek(u64::from(start) * 64);
+    assert_eq!(cipher.get_core().remaining_blocks(), 1);
+    let mut buf = [0; 128];
+    assert!(cipher.try_apply_keystream(&mut buf).is_err());
+    cipher.apply_keystream(&mut buf[..64]);
+    assert_eq!(buf[..64], EXPECTED_LONG_CARRY[..64]);
+    assert!(cipher.try_apply_keystream(&mut [0; 1]).is_err());
+    assert_eq!(cipher.current_pos::<u64>(), u64::from(u32::MAX) * 64);
+
+    let core = Core32::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.remaining_blocks(), u64::from(u32::MAX));
+    assert_eq!(core.get_block_pos(), 0u32);
+
+    // the upper counter word is kept, e.g. when it is part of the nonce
+    let mut state = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into()).into_raw_state();
+    state[9] = 1;
+    let mut core = Core32::from_raw_state(state);
+    assert_eq!(core.get_block_pos(), 0);
+    assert_eq!(core.block()[..], EXPECTED_LONG_CARRY[128..192]);
+    core.set_block_pos(1);
+    assert_eq!(core.block()[..], EXPECTED_LONG_CARRY[192..]);
+    assert_eq!(core.get_block_pos(), 2);
+    assert_eq!(core.into_raw_state()[8..10], [2, 1]);
+}
+
+#[test]
+fn salsa20_2mib_matches_soft() {
+    let mut buf = vec![0u8; 2 << 20];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let mut expected = buf.clone();
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.apply_keystream(&mut buf);
+    assert!(buf == expected);
+}
+
+#[test]
+fn salsa_variants_match_soft() {
+    use cipher::{
+        consts::{U4, U6},
+        StreamCipherCoreWrapper,
+    };
+    use salsa20::SalsaCore;
+
+    // Cover every tail length up to two full parallel groups of the widest
+    // backend, whichever backend the runtime dispatch picks on this CPU.
+    fn check<C: KeyIvInit<KeySize = U32, IvSize = U8> + StreamCipher>(double_rounds: usize) {
+        for len in 1..=(16 * 64 + 1) {
+            let mut buf = vec![0u8; len];
+            let mut expected = buf.clone();
+            soft_keystream(&KEY_LONG, &IV_LONG, double_rounds, &mut expected);
+
+            let mut cipher = C::new(&KEY_LONG.into(), &IV_LONG.into());
+            cipher.apply_keystream(&mut buf);
+            assert!(buf == expected, "mismatch at length {len}");
+        }
+    }
+
+    check::<StreamCipherCoreWrapper<SalsaCore<U4, U32>>>(4);
+    check::<StreamCipherCoreWrapper<SalsaCore<U6, U32>>>(6);
+    check::<Salsa20>(10);
+}
+
+/// Raw states are always given in canonical word order, so the counter words
+/// are 8 (low) and 9 (high) whichever backend is active.
+/// The key and nonce are decoded as little-endian words, also on big-endian
+/// targets (CI runs the tests on `s390x`)
+#[test]
+fn salsa20_little_endian_words() {
+    use cipher::consts::U10;
+    use salsa20::SalsaCore;
+
+    let core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let words = core.state_words();
+    assert_eq!(words[0], 0x6170_7865);
+    assert_eq!(
+        words[1..5],
+        [0x0403_0201, 0x0807_0605, 0x0c0b_0a09, 0x100f_0e0d]
+    );
+    assert_eq!(words[6..8], [0x0104_0103, 0x0602_0905]);
+    assert_eq!(
+        words[11..15],
+        [0x1413_1211, 0x1817_1615, 0x1c1b_1a19, 0x201f_1e1d]
+    );
+
+    let mut core = core;
+    assert_eq!(core.block()[..], EXPECTED_LONG[..64]);
+}
+
+/// The snapshot is in canonical order whatever the layout of the active
+/// backend (CI also runs this with `salsa20_force_soft`)
+#[test]
+fn salsa20_snapshot() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let word = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos((1 << 32) - 2);
+
+    let (key, nonce, counter) = core.snapshot();
+    let expected_key: Vec<u32> = KEY_LONG.chunks(4).map(word).collect();
+    assert_eq!(key[..], expected_key[..]);
+    assert_eq!(nonce, [word(&IV_LONG[..4]), word(&IV_LONG[4..])]);
+    assert_eq!(counter, (1 << 32) - 2);
+
+    let mut restored = SalsaCore::<U10, U32>::from_snapshot(key, nonce, counter);
+    assert_eq!(restored, core);
+    let mut buf = [0u8; 256];
+    for block in buf.chunks_mut(64) {
+        block.copy_from_slice(&restored.block());
+    }
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+
+    // a 128-bit key fills both key slots
+    let core = SalsaCore::<U10, U16>::new(&KEY_LONG[..16].try_into().unwrap(), &IV0.into());
+    let (key, nonce, counter) = core.snapshot();
+    assert_eq!(key[..4], key[4..]);
+    assert_eq!(key[..4], expected_key[..4]);
+    assert_eq!((nonce, counter), ([0; 2], 0));
+    assert_eq!(
+        SalsaCore::<U10, U16>::from_snapshot(key, nonce, counter),
+        core
+    );
+}
+
+#[test]
+fn salsa20_raw_state_counter() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let word = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    let c = THIRTY_TWO_BYTE_KEY_CONSTANTS;
+    let mut state = [0u32; 16];
+    state[0] = c[0];
+    for i in 0..4 {
+        state[1 + i] = word(&KEY_LONG[4 * i..][..4]);
+        state[11 + i] = word(&KEY_LONG[16 + 4 * i..][..4]);
+    }
+    state[5] = c[1];
+    state[6] = word(&IV_LONG[..4]);
+    state[7] = word(&IV_LONG[4..]);
+    state[8] = 0xffff_fffe;
+    state[10] = c[2];
+    state[15] = c[3];
+
+    let core = SalsaCore::<U10, U32>::from_raw_state(state);
+    assert_eq!(core.get_block_pos(), (1 << 32) - 2);
+
+    // the same logical layout is reported on all targets
+    let mut fresh = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    fresh.set_block_pos((1 << 32) - 2);
+    assert_eq!(fresh.state_words(), state);
+    assert_eq!(core.state_words(), state);
+
+    // the same canonical words give the same core on all targets
+    let core_le = SalsaCore::<U10, U32>::from_raw_state_le(state);
+    assert_eq!(core_le, core);
+    assert_eq!(core_le.into_raw_state(), state);
+
+    let mut cipher = StreamCipherCoreWrapper::from_core(core);
+    let mut buf = [0; 256];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+    assert_eq!(cipher.get_core().get_block_pos(), (1 << 32) + 2);
+}
+
+#[test]
+fn salsa20_generate_keystream() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    for len in [0, 1, 63, 64, 65, 255, 256, 1000] {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        let mut keystream = vec![0u8; len];
+        assert_eq!(core.generate_keystream(&mut keystream), keystream.len());
+        assert_eq!(core.get_block_pos(), len.div_ceil(64) as u64);
+
+        let mut expected = plaintext.clone();
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.apply_keystream(&mut expected);
+
+        let xored: Vec<u8> = plaintext
+            .iter()
+            .zip(keystream.iter())
+            .map(|(p, k)| p ^ k)
+            .collect();
+        assert_eq!(xored, expected, "mismatch at length {len}");
+    }
+}
+
+#[test]
+fn salsa20_encrypt() {
+    for len in [0, 1, 63, 64, 65, 1000] {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+
+        let mut buf = plaintext.clone();
+        salsa20::encrypt(&KEY_LONG.into(), &IV_LONG.into(), &mut buf);
+
+        let mut expected = plaintext.clone();
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.apply_keystream(&mut expected);
+        assert_eq!(buf, expected, "mismatch at length {len}");
+
+        #[cfg(feature = "reduced-round")]
+        {
+            let mut buf = plaintext.clone();
+            salsa20::encrypt12(&KEY_LONG.into(), &IV_LONG.into(), &mut buf);
+            let mut expected = plaintext.clone();
+            let mut cipher = Salsa12::new(&KEY_LONG.into(), &IV_LONG.into());
+            cipher.apply_keystream(&mut expected);
+            assert_eq!(buf, expected, "Salsa20/12 mismatch at length {len}");
+
+            let mut buf = plaintext.clone();
+            salsa20::encrypt8(&KEY_LONG.into(), &IV_LONG.into(), &mut buf);
+            let mut expected = plaintext.clone();
+            let mut cipher = Salsa8::new(&KEY_LONG.into(), &IV_LONG.into());
+            cipher.apply_keystream(&mut expected);
+            assert_eq!(buf, expected, "Salsa20/8 mismatch at length {len}");
+        }
+    }
+}
+
+#[test]
+fn salsa20_block() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY1.into(), &IV0.into());
+    assert_eq!(core.block(), EXPECTED_KEY1_IV0);
+    assert_eq!(core.get_block_pos(), 1);
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut blocks = [0u8; 128];
+    blocks[..64].copy_from_slice(&core.block());
+    blocks[64..].copy_from_slice(&core.block());
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut expected = [0u8; 128];
+    assert_eq!(core.generate_keystream(&mut expected), expected.len());
+    assert_eq!(blocks, expected);
+}
+
+#[test]
+fn salsa20_gen_ks_block() {
+    use cipher::{array::Array, consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos((1 << 32) - 2);
+    let mut expected = core.clone();
+
+    let mut block = Array::default();
+    for i in 0..4 {
+        core.gen_ks_block(&mut block);
+        assert_eq!(block, expected.block(), "block {i}");
+        assert_eq!(block[..], EXPECTED_LONG_CARRY[64 * i..][..64]);
+        assert_eq!(core.get_block_pos(), (1 << 32) - 1 + i as u64);
+    }
+}
+
+#[test]
+fn salsa20_xor_block() {
+    use cipher::{array::Array, consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY1.into(), &IV0.into());
+    let mut block = Array::default();
+    core.xor_block(&mut block);
+    assert_eq!(block, EXPECTED_KEY1_IV0);
+    assert_eq!(core.get_block_pos(), 1);
+
+    // consecutive calls process consecutive blocks
+    let plaintext: [u8; 128] = core::array::from_fn(|i| i as u8);
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut blocks: [Array<u8, _>; 2] = [
+        plaintext[..64].try_into().unwrap(),
+        plaintext[64..].try_into().unwrap(),
+    ];
+    for block in &mut blocks {
+        core.xor_block(block);
+    }
+    assert_eq!(core.get_block_pos(), 2);
+
+    let mut expected = plaintext;
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+    assert_eq!(blocks.concat(), expected);
+}
+
+#[test]
+fn salsa20_keystream_at() {
+    use cipher::{
+        consts::{U10, U4},
+        StreamCipherSeekCore,
+    };
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(5);
+    for n in [0, 1, 2, 3, 5, 1 << 32, u64::MAX - 1] {
+        let mut seeked = core.clone();
+        seeked.set_block_pos(n);
+        assert_eq!(core.keystream_at(n), seeked.block(), "block {n}");
+        assert_eq!(core.get_block_pos(), 5);
+    }
+    assert_eq!(core.keystream_at(1)[..], EXPECTED_LONG[64..128]);
+    assert_eq!(
+        core.keystream_at((1 << 32) - 1)[..],
+        EXPECTED_LONG_CARRY[64..128]
+    );
+
+    let core = SalsaCore::<U10, U32, U4>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.keystream_at(1)[..], EXPECTED_LONG[64..128]);
+}
+
+#[test]
+fn salsa20_new_const() {
+    use cipher::{
+        consts::{U10, U16},
+        StreamCipherSeekCore,
+    };
+    use salsa20::SalsaCore;
+
+    static STATIC_CORE: SalsaCore<U10, U32> = SalsaCore::new_const(&KEY1, &IV0);
+    assert_eq!(STATIC_CORE.get_block_pos(), 0);
+
+    const CORE: SalsaCore<U10, U32> = SalsaCore::new_const(&KEY1, &IV0);
+    const CORE_LONG: SalsaCore<U10, U32> = SalsaCore::new_const(&KEY_LONG, &IV_LONG);
+    const CORE_128: SalsaCore<U10, U16> =
+        SalsaCore::new_const(&hex!("0102030405060708090A0B0C0D0E0F10"), &IV_LONG);
+
+    let mut core = CORE;
+    assert_eq!(core.block(), EXPECTED_KEY1_IV0);
+
+    let mut expected = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut core = CORE_LONG;
+    assert_eq!(core.block(), expected.block());
+
+    let key = hex!("0102030405060708090A0B0C0D0E0F10");
+    let mut expected = SalsaCore::<U10, U16>::new(&key.into(), &IV_LONG.into());
+    let mut core = CORE_128;
+    assert_eq!(core.block(), expected.block());
+}
+
+#[test]
+fn salsa20_clone_mid_stream() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    // fork the core in the middle of a parallel batch
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.generate_keystream(&mut [0u8; 192]), 192);
+    let mut fork = core.clone();
+    assert_eq!(fork.get_block_pos(), 3);
+
+    let (mut a, mut b) = ([0u8; 1000], [0u8; 1000]);
+    assert_eq!(core.generate_keystream(&mut a), a.len());
+    assert_eq!(fork.generate_keystream(&mut b), b.len());
+    assert_eq!(a, b);
+    assert_eq!(core.get_block_pos(), fork.get_block_pos());
+
+    // fork the wrapped ciphers in the middle of a block
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.apply_keystream(&mut [0u8; 100]);
+    let mut fork = cipher.clone();
+    let (mut a, mut b) = ([0u8; 1000], [0u8; 1000]);
+    cipher.apply_keystream(&mut a);
+    fork.apply_keystream(&mut b);
+    assert_eq!(a, b);
+
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    cipher.apply_keystream(&mut [0u8; 100]);
+    let mut fork = cipher.clone();
+    let (mut a, mut b) = ([0u8; 1000], [0u8; 1000]);
+    cipher.apply_keystream(&mut a);
+    fork.apply_keystream(&mut b);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn salsa20_debug_redacts_state() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    fn assert_redacted(s: &str) {
+        let word = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+        let words = KEY_LONG
+            .chunks(4)
+            .chain(KEY_XSALSA20.chunks(4))
+            .chain(IV_LONG.chunks(4))
+            .map(word)
+            .chain(THIRTY_TWO_BYTE_KEY_CONSTANTS);
+        for w in words {
+            assert!(!s.contains(&format!("{w}")), "{s} leaks {w}");
+            assert!(!s.contains(&format!("{w:x}")), "{s} leaks {w:x}");
+            assert!(!s.contains(&format!("{w:X}")), "{s} leaks {w:X}");
+        }
+    }
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(0x1234_5678_9abc);
+    let s = format!("{core:?}");
+    assert_eq!(
+        s,
+        "SalsaCore { double_rounds: 10, key_size: 32, block_pos: 20015998343868, .. }"
+    );
+    assert_redacted(&s);
+    assert_redacted(&format!("{core:#?}"));
+
+    let mut core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    core.set_block_pos(42);
+    let s = format!("{core:?}");
+    assert!(s.starts_with("XSalsaCore {"));
+    assert!(s.contains("block_pos: 42"));
+    assert_redacted(&s);
+}
+
+/// Canonical state words for `KEY_LONG`, `IV_LONG` and block position `pos`
+#[cfg(feature = "serde")]
+fn canonical_state_long(pos: u64) -> [u32; 16] {
+    let word = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+    let c = THIRTY_TWO_BYTE_KEY_CONSTANTS;
+    let mut state = [0u32; 16];
+    state[0] = c[0];
+    for i in 0..4 {
+        state[1 + i] = word(&KEY_LONG[4 * i..][..4]);
+        state[11 + i] = word(&KEY_LONG[16 + 4 * i..][..4]);
+    }
+    state[5] = c[1];
+    state[6] = word(&IV_LONG[..4]);
+    state[7] = word(&IV_LONG[4..]);
+    state[8] = pos as u32;
+    state[9] = (pos >> 32) as u32;
+    state[10] = c[2];
+    state[15] = c[3];
+    state
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn salsa20_serde_roundtrip() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, SalsaState};
+
+    let pos = (1 << 32) - 2;
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(pos);
+
+    // the checkpoint uses the canonical word order whatever the backend layout
+    let encoded = serde_json::to_string(&core.to_state()).unwrap();
+    let words = canonical_state_long(pos).map(|w| w.to_string()).join(",");
+    assert_eq!(encoded, format!("{{\"state\":[{words}]}}"));
+
+    let decoded: SalsaState = serde_json::from_str(&encoded).unwrap();
+    assert!(decoded == core.to_state());
+    let mut restored = SalsaCore::<U10, U32>::from_state(&decoded);
+    assert_eq!(restored.get_block_pos(), pos);
+
+    let (mut a, mut b) = ([0u8; 256], [0u8; 256]);
+    assert_eq!(core.generate_keystream(&mut a), a.len());
+    assert_eq!(restored.generate_keystream(&mut b), b.len());
+    assert_eq!(a, b);
+    assert_eq!(a, EXPECTED_LONG_CARRY);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn salsa20_serde_restore_continues_stream() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, SalsaState};
+
+    // checkpoint written in the canonical layout by another target
+    let words = canonical_state_long(1).map(|w| w.to_string()).join(",");
+    let state: SalsaState = serde_json::from_str(&format!("{{\"state\":[{words}]}}")).unwrap();
+    let core = SalsaCore::<U10, U32>::from_state(&state);
+    assert_eq!(core.get_block_pos(), 1);
+
+    let mut cipher = StreamCipherCoreWrapper::from_core(core);
+    let mut buf = [0u8; 192];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG[64..]);
+
+    // checkpoint an in-progress stream and resume it
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 256];
+    cipher.apply_keystream(&mut buf[..128]);
+    let state = serde_json::to_vec(&cipher.get_core().to_state()).unwrap();
+
+    let state: SalsaState = serde_json::from_slice(&state).unwrap();
+    let mut cipher = StreamCipherCoreWrapper::from_core(SalsaCore::<U10, U32>::from_state(&state));
+    cipher.apply_keystream(&mut buf[128..]);
+    assert_eq!(buf, EXPECTED_LONG);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn salsa20_io_roundtrip() {
+    use salsa20::{SalsaReader, SalsaWriter};
+    use std::io::{self, Cursor, Read, Write};
+
+    /// Reader and writer which only transfer a few bytes per call
+    struct Short<T>(T, usize);
+
+    impl<T: Read> Read for Short<T> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.1 = self.1 % 997 + 1;
+            let n = buf.len().min(self.1);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    impl<T: Write> Write for Short<T> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1 = self.1 % 997 + 1;
+            let n = buf.len().min(self.1);
+            self.0.write(&buf[..n])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    let plaintext: Vec<u8> = (0..3 << 20).map(|i| (i % 251) as u8).collect();
+    let mut expected = plaintext.clone();
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+
+    let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut writer = SalsaWriter::new(Short(Cursor::new(Vec::new()), 0), cipher);
+    io::copy(&mut Cursor::new(&plaintext), &mut writer).unwrap();
+    writer.flush().unwrap();
+    let (Short(ciphertext, _), cipher) = writer.into_inner();
+    let ciphertext = ciphertext.into_inner();
+    assert!(ciphertext == expected);
+    assert_eq!(cipher.current_pos::<usize>(), plaintext.len());
+
+    let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut reader = SalsaReader::new(Short(Cursor::new(ciphertext), 0), cipher);
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted).unwrap();
+    assert!(decrypted == plaintext);
+    assert_eq!(reader.cipher().current_pos::<usize>(), plaintext.len());
+}
+
+/// Writer whose inner writer accepts at most 100 bytes per call and fails
+/// every third call, which must not reuse the keystream of rejected bytes.
+#[cfg(feature = "std")]
+#[test]
+fn salsa20_writer_partial_writes() {
+    use salsa20::SalsaWriter;
+    use std::io::{self, ErrorKind, Write};
+
+    struct Partial(Vec<u8>, usize);
+
+    impl Write for Partial {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1 += 1;
+            if self.1 % 3 == 0 {
+                return Err(ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(100);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let plaintext: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    let mut expected = plaintext.clone();
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+
+    let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut writer = SalsaWriter::new(Partial(Vec::new(), 0), cipher);
+    for chunk in plaintext.chunks(5000) {
+        writer.write_all(chunk).unwrap();
+    }
+    let (Partial(ciphertext, _), cipher) = writer.into_inner();
+    assert!(ciphertext == expected);
+    assert_eq!(cipher.current_pos::<usize>(), plaintext.len());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn salsa20_framing_roundtrip() {
+    use salsa20::{UnauthenticatedFrameReader, UnauthenticatedFrameWriter};
+    use std::io::ErrorKind;
+
+    let sizes = [0, 1, 63, 64, 65, 100, 4096, 5000, 0, 7];
+    let frames: Vec<Vec<u8>> = sizes
+        .iter()
+        .map(|&n| (0..n).map(|i| (i % 251) as u8).collect())
+        .collect();
+
+    let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut writer = UnauthenticatedFrameWriter::new(Vec::new(), cipher);
+    for frame in &frames {
+        writer.write_frame(frame).unwrap();
+    }
+    let (transport, _) = writer.into_inner();
+    assert_eq!(
+        transport.len(),
+        4 * sizes.len() + sizes.iter().sum::<usize>()
+    );
+
+    // the keystream continues across frames
+    let mut expected = frames.concat();
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+    let mut ciphertext = Vec::new();
+    let mut rest = &transport[..];
+    for &n in &sizes {
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap());
+        assert_eq!(len as usize, n);
+        ciphertext.extend_from_slice(&rest[4..][..n]);
+        rest = &rest[4 + n..];
+    }
+    assert!(ciphertext == expected);
+
+    let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut reader = UnauthenticatedFrameReader::new(&transport[..], cipher);
+    for frame in &frames {
+        assert_eq!(&reader.read_frame().unwrap().unwrap(), frame);
+    }
+    assert!(reader.read_frame().unwrap().is_none());
+
+    // truncated within the length prefix of the 1-byte frame and within the
+    // data of the 63-byte frame
+    for truncated in [&transport[4..6], &transport[9..13 + 10]] {
+        let cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        let mut reader = UnauthenticatedFrameReader::new(truncated, cipher);
+        let err = reader.read_frame().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
+
+#[test]
+fn salsa20_apply_keystream_vectored() {
+    use salsa20::ApplyKeystreamVectored;
+
+    let plaintext: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+    let mut expected = plaintext.clone();
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+
+    for sizes in [
+        &[1000][..],
+        &[0, 1000],
+        &[3, 61, 64, 1, 0, 127, 744],
+        &[64, 64, 872],
+    ] {
+        let mut buf = plaintext.clone();
+        let mut bufs = Vec::new();
+        let mut rest = &mut buf[..];
+        for &n in sizes {
+            let (head, tail) = rest.split_at_mut(n);
+            bufs.push(head);
+            rest = tail;
+        }
+
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.apply_keystream_vectored(&mut bufs);
+        assert_eq!(cipher.current_pos::<usize>(), plaintext.len());
+        assert!(buf == expected, "mismatch for {sizes:?}");
+    }
+
+    // buffers up to the one which does not fit are processed
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.seek(u128::from(u64::MAX) * 64 - 10);
+    let mut expected = [0u8; 8];
+    cipher.clone().apply_keystream(&mut expected);
+    let (mut a, mut b) = ([0u8; 8], [0u8; 8]);
+    assert!(cipher
+        .try_apply_keystream_vectored(&mut [&mut a, &mut b])
+        .is_err());
+    assert_eq!(a, expected);
+    assert_eq!(b, [0; 8]);
+    assert_eq!(cipher.current_pos::<u128>(), u128::from(u64::MAX) * 64 - 2);
+}
+
+#[test]
+fn salsa_dyn_variants() {
+    use salsa20::{DynSalsa, SalsaVariant};
+
+    fn check<C: KeyIvInit<KeySize = U32, IvSize = U8> + StreamCipher>(variant: SalsaVariant) {
+        let plaintext: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let mut expected = plaintext.clone();
+        C::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+
+        let mut cipher = DynSalsa::new(variant, &KEY_LONG.into(), &IV_LONG.into());
+        assert_eq!(cipher.variant(), variant);
+        let mut buf = plaintext.clone();
+        cipher.apply_keystream(&mut buf[..100]);
+        cipher.clone().apply_keystream(&mut buf[100..]);
+        assert_eq!(cipher.current_pos::<u64>(), 100);
+        assert_eq!(buf, expected, "{variant:?}");
+
+        let mut cipher = DynSalsa::new(variant, &KEY_LONG.into(), &IV_LONG.into());
+        cipher.seek(7u32);
+        let mut buf = plaintext[7..].to_vec();
+        cipher.apply_keystream(&mut buf);
+        assert_eq!(buf, expected[7..]);
+    }
+
+    check::<Salsa20>(SalsaVariant::Salsa20);
+    assert_eq!(SalsaVariant::Salsa20.rounds(), 20);
+    #[cfg(feature = "reduced-round")]
+    {
+        check::<Salsa12>(SalsaVariant::Salsa12);
+        check::<Salsa8>(SalsaVariant::Salsa8);
+        assert_eq!(SalsaVariant::Salsa12.rounds(), 12);
+        assert_eq!(SalsaVariant::Salsa8.rounds(), 8);
+    }
+
+    let cipher = DynSalsa::new(SalsaVariant::Salsa20, &KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(format!("{cipher:?}"), "DynSalsa { variant: Salsa20, .. }");
+}
+
+#[test]
+fn salsa20_counter_overflow() {
+    use cipher::{consts::U10, StreamCipherCore, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let last = u64::MAX - 2;
+
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.seek(u128::from(last) * 64);
+    let mut buf = [0u8; 129];
+    assert!(cipher.try_apply_keystream(&mut buf).is_err());
+    assert_eq!(buf, [0u8; 129]);
+    assert!(cipher.try_apply_keystream(&mut buf[..128]).is_ok());
+    assert_eq!(cipher.current_pos::<u128>(), u128::from(u64::MAX) * 64);
+    assert!(cipher.try_apply_keystream(&mut buf[..1]).is_err());
+
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    cipher.seek(u128::from(last) * 64 + 10);
+    assert!(cipher.try_apply_keystream(&mut [0u8; 119]).is_err());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 118]).is_ok());
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(last);
+    assert_eq!(core.remaining_blocks(), 2);
+    assert_eq!(StreamCipherCore::remaining_blocks(&core), Some(2));
+    let mut buf = [0u8; 192];
+    assert!(core.apply_keystream_blocks(&mut buf).is_err());
+    assert_eq!(core.get_block_pos(), last);
+    assert!(core.apply_keystream_blocks(&mut buf[..128]).is_ok());
+    assert_eq!(core.get_block_pos(), u64::MAX);
+    assert!(core.apply_keystream_blocks(&mut buf[..64]).is_err());
+}
+
+#[test]
+fn salsa20_remaining() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.remaining_blocks(), u64::MAX);
+    assert_eq!(core.remaining_bytes(), None);
+
+    // the byte count fits into `u64` from block position 2^64 - 2^58 on
+    let first = u64::MAX - (1 << 58) + 1;
+    core.set_block_pos(first - 1);
+    assert_eq!(core.remaining_blocks(), 1 << 58);
+    assert_eq!(core.remaining_bytes(), None);
+    core.set_block_pos(first);
+    assert_eq!(core.remaining_blocks(), (1 << 58) - 1);
+    assert_eq!(core.remaining_bytes(), Some(u64::MAX - 63));
+
+    core.set_block_pos(u64::MAX - 2);
+    assert_eq!(core.remaining_blocks(), 2);
+    assert_eq!(core.remaining_bytes(), Some(128));
+    core.apply_keystream_blocks(&mut [0; 64]).unwrap();
+    assert_eq!(core.remaining_blocks(), 1);
+    assert_eq!(core.remaining_bytes(), Some(64));
+    core.apply_keystream_blocks(&mut [0; 64]).unwrap();
+    assert_eq!(core.remaining_blocks(), 0);
+    assert_eq!(core.remaining_bytes(), Some(0));
+    assert!(core.apply_keystream_blocks(&mut [0; 64]).is_err());
+
+    let mut core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    assert_eq!(core.remaining_blocks(), u64::MAX);
+    core.set_block_pos(u64::MAX - 5);
+    assert_eq!(core.remaining_blocks(), 5);
+    assert_eq!(core.remaining_bytes(), Some(320));
+
+    // through the wrapper
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.seek(u128::from(u64::MAX - 3) * 64);
+    assert_eq!(cipher.get_core().remaining_blocks(), 3);
+    assert_eq!(cipher.get_core().remaining_bytes(), Some(192));
+    let cipher = StreamCipherCoreWrapper::from_core(core);
+    assert_eq!(cipher.get_core().remaining_bytes(), Some(320));
+}
+
+#[test]
+fn salsa20_try_set_block_pos() {
+    use cipher::{
+        consts::{U10, U4},
+        StreamCipherSeekCore,
+    };
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.try_set_block_pos(u64::MAX - 1).unwrap();
+    assert_eq!(core.get_block_pos(), u64::MAX - 1);
+    assert!(core.try_set_block_pos(u64::MAX).is_err());
+    assert_eq!(core.get_block_pos(), u64::MAX - 1);
+    // the last block can still be generated
+    core.apply_keystream_blocks(&mut [0; 64]).unwrap();
+    assert!(core.apply_keystream_blocks(&mut [0; 64]).is_err());
+
+    let mut core = SalsaCore::<U10, U32, U4>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.try_set_block_pos(u32::MAX - 1).unwrap();
+    assert!(core.try_set_block_pos(u32::MAX).is_err());
+    assert_eq!(core.get_block_pos(), u32::MAX - 1);
+
+    let mut core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    core.try_set_block_pos(0).unwrap();
+    assert!(core.try_set_block_pos(u64::MAX).is_err());
+    assert_eq!(core.get_block_pos(), 0);
+}
+
+#[test]
+fn salsa20_new_with_counter() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    for counter in [
+        0,
+        1,
+        3,
+        1 << 31,
+        (1 << 32) - 1,
+        1 << 32,
+        0x0123_4567_89ab_cdef,
+    ] {
+        let mut core =
+            SalsaCore::<U10, U32>::new_with_counter(&KEY_LONG.into(), &IV_LONG.into(), counter);
+        assert_eq!(core.get_block_pos(), counter);
+
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.seek(u128::from(counter) * 64);
+        let mut expected = [0u8; 64];
+        cipher.apply_keystream(&mut expected);
+
+        assert_eq!(core.block(), expected, "mismatch at counter {counter}");
+    }
+
+    let mut core = SalsaCore::<U10, U32>::new_with_counter(&KEY_LONG.into(), &IV_LONG.into(), 1);
+    let mut buf = [0u8; 192];
+    assert_eq!(core.generate_keystream(&mut buf), buf.len());
+    assert_eq!(buf, EXPECTED_LONG[64..]);
+}
+
+#[test]
+fn salsa20_new_with_constants() {
+    use cipher::consts::U10;
+    use salsa20::{constants, SalsaCore};
+
+    let mut core =
+        SalsaCore::<U10, U32>::new_with_constants(&KEY_LONG.into(), &IV_LONG.into(), constants(32));
+    let mut buf = [0u8; 128];
+    core.apply_keystream_blocks(&mut buf).unwrap();
+    assert_eq!(buf, EXPECTED_LONG[..128]);
+
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&KEY_LONG[..16]);
+    let mut core =
+        SalsaCore::<U10, U16>::new_with_constants(&key.into(), &IV_LONG.into(), constants(16));
+    let mut expected = SalsaCore::<U10, U16>::new(&key.into(), &IV_LONG.into());
+    assert_eq!(core.block(), expected.block());
+
+    // a personalization string instead of "expand 32-byte k"
+    let sigma = *b"my app kdf v1.0!";
+    let sigma = [0, 1, 2, 3].map(|i| u32::from_le_bytes(sigma[4 * i..][..4].try_into().unwrap()));
+    let core = SalsaCore::<U10, U32>::new_with_constants(&KEY_LONG.into(), &IV_LONG.into(), sigma);
+    let state = core.into_raw_state();
+    let standard = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into()).into_raw_state();
+    for i in 0..16 {
+        match i {
+            0 | 5 | 10 | 15 => assert_eq!(state[i], sigma[i / 5]),
+            _ => assert_eq!(state[i], standard[i]),
+        }
+    }
+    let mut core =
+        SalsaCore::<U10, U32>::new_with_constants(&KEY_LONG.into(), &IV_LONG.into(), sigma);
+    let mut buf = [0u8; 128];
+    core.apply_keystream_blocks(&mut buf).unwrap();
+    assert_ne!(buf, EXPECTED_LONG[..128]);
+}
+
+#[test]
+fn salsa_rounds_and_key_size() {
+    use cipher::consts::{U10, U4, U6};
+    use salsa20::SalsaCore;
+
+    let salsa20 = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(salsa20.get_core().rounds(), 20);
+    assert_eq!(salsa20.get_core().key_size(), 32);
+
+    #[cfg(feature = "reduced-round")]
+    {
+        let salsa12 = Salsa12::new(&KEY_LONG.into(), &IV_LONG.into());
+        assert_eq!(salsa12.get_core().rounds(), 12);
+
+        let salsa8 = Salsa8::new(&KEY_LONG.into(), &IV_LONG.into());
+        assert_eq!(salsa8.get_core().rounds(), 8);
+    }
+
+    const ROUNDS: [usize; 3] = [
+        SalsaCore::<U4, U32>::ROUNDS,
+        SalsaCore::<U6, U32>::ROUNDS,
+        SalsaCore::<U10, U32>::ROUNDS,
+    ];
+    assert_eq!(ROUNDS, [8, 12, 20]);
+    assert_eq!(SalsaCore::<U10, U16>::KEY_SIZE, 16);
+}
+
+/// Zeroizing the backend working state must not clobber the block counter
+/// written back into the cipher state.
+#[cfg(feature = "zeroize")]
+#[test]
+fn salsa20_zeroize_backend_state() {
+    let mut expected = [0u8; 1024];
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 1024];
+    for chunk in buf.chunks_mut(64 + 7) {
+        cipher.apply_keystream(chunk);
+    }
+    assert_eq!(buf, expected);
+    assert_eq!(cipher.current_pos::<usize>(), 1024);
+}
+
+#[test]
+fn salsa20_keystream_blocks() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let expected = [core.block(), core.block(), core.block()];
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let blocks: Vec<_> = core.keystream_blocks().take(3).collect();
+    assert_eq!(blocks, expected);
+    assert_eq!(core.get_block_pos(), 3);
+
+    for (block, expected) in core.keystream_blocks().zip(EXPECTED_LONG[192..].chunks(64)) {
+        assert_eq!(block[..], *expected);
+    }
+
+    // the iterator stops instead of wrapping the block counter
+    core.set_block_pos(u64::MAX - 2);
+    assert_eq!(core.keystream_blocks().count(), 2);
+    assert_eq!(core.get_block_pos(), u64::MAX);
+    assert!(core.keystream_blocks().next().is_none());
+}
+
+#[test]
+fn salsa20_keystream_bytes() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let block = core.block();
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let bytes: Vec<u8> = core.keystream_bytes().take(64).collect();
+    assert_eq!(bytes[..], block[..]);
+    assert_eq!(core.get_block_pos(), 1);
+
+    // refills across block boundaries
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let bytes: Vec<u8> = core.keystream_bytes().take(200).collect();
+    assert_eq!(bytes[..], EXPECTED_LONG[..200]);
+    assert_eq!(core.get_block_pos(), 4);
+
+    // stops instead of wrapping the block counter
+    core.set_block_pos(u64::MAX - 1);
+    assert_eq!(core.keystream_bytes().count(), 64);
+    assert_eq!(core.get_block_pos(), u64::MAX);
+}
+
+#[test]
+fn salsa20_seek_to_block() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper};
+    use salsa20::SalsaCore;
+
+    for block in [0, 1, 5, (1 << 32) - 1, 1 << 32, u64::MAX - 1] {
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        core.seek_to_block(block);
+        assert_eq!(core.current_block(), block);
+        let mut cipher = StreamCipherCoreWrapper::from_core(core);
+
+        let mut expected = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        expected.seek(u128::from(block) * 64);
+
+        let (mut a, mut b) = ([0u8; 64], [0u8; 64]);
+        cipher.apply_keystream(&mut a);
+        expected.apply_keystream(&mut b);
+        assert_eq!(a, b, "mismatch at block {block}");
+        assert_eq!(cipher.get_core().current_block(), block + 1);
+    }
+}
+
+#[test]
+fn salsa20_keystream_xor_inout() {
+    use cipher::{consts::U10, InOutBuf, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    for len in [0, 1, 63, 64, 65, 255, 256, 257, 1000] {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        let mut ciphertext = vec![0u8; len];
+        let buf = InOutBuf::new(&plaintext, &mut ciphertext).unwrap();
+        core.keystream_xor_inout(buf).unwrap();
+        assert_eq!(core.get_block_pos(), len.div_ceil(64) as u64);
+
+        let mut expected = plaintext.clone();
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.apply_keystream(&mut expected);
+
+        assert_eq!(ciphertext, expected, "mismatch at length {len}");
+        assert!(plaintext.iter().enumerate().all(|(i, &b)| b == i as u8));
+    }
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::MAX - 1);
+    let mut out = [0u8; 65];
+    let buf = InOutBuf::new(&[1u8; 65], &mut out).unwrap();
+    assert!(core.keystream_xor_inout(buf).is_err());
+    assert_eq!(out, [0u8; 65]);
+    assert_eq!(core.get_block_pos(), u64::MAX - 1);
+}
+
+/// Seeking must agree with the reference keystream whether the SIMD or the
+/// software backend is active (CI also runs this with `salsa20_force_soft` and
+/// `salsa20_force_sse2`).
+#[test]
+fn salsa20_seek_matches_soft() {
+    let mut expected = vec![0u8; 64 * 64];
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+
+    for pos in [0, 1, 63, 64, 65, 200, 511, 1000, 2049, 64 * 63] {
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.seek(pos);
+        assert_eq!(cipher.current_pos::<u64>(), pos as u64);
+        let mut buf = vec![0u8; expected.len() - pos];
+        cipher.apply_keystream(&mut buf);
+        assert!(buf == expected[pos..], "mismatch at position {pos}");
+        assert_eq!(cipher.current_pos::<usize>(), expected.len());
+    }
+}
+
+/// Byte-granular seeks across the carry into the high counter word, which the
+/// x86 SIMD layout stores in `state[5]` instead of `state[9]`.
+#[test]
+fn salsa20_seek_unaligned() {
+    let base = ((1u64 << 32) - 2) * 64;
+    for off in [1, 37, 63, 64, 65, 100, 129, 255] {
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.seek(base + off as u64);
+        let mut buf = [0u8; 256];
+        cipher.apply_keystream(&mut buf[off..]);
+        assert_eq!(
+            buf[off..],
+            EXPECTED_LONG_CARRY[off..],
+            "mismatch at offset {off}"
+        );
+        assert_eq!(cipher.current_pos::<u64>(), base + 256);
+    }
+
+    let plaintext: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    let mut expected = plaintext.clone();
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+
+    // encrypt the tail first, then the head in front of it
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = plaintext.clone();
+    cipher.seek(100);
+    cipher.apply_keystream(&mut buf[100..]);
+    assert_eq!(buf[100..], expected[100..]);
+    cipher.seek(0);
+    cipher.apply_keystream(&mut buf[..100]);
+    assert_eq!(buf, expected);
+
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, plaintext);
+}
+
+/// Seeking with the integer types accepted by `SeekNum`, near block
+/// boundaries and the carry into the high counter word.
+#[test]
+fn salsa20_seek_num_types() {
+    let new = || Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let keystream_at = |pos: u64, buf: &mut [u8]| {
+        let mut cipher = new();
+        cipher.seek(pos);
+        cipher.apply_keystream(buf);
+    };
+
+    // `u32` byte positions, the type used in the README, up to the end of
+    // their range
+    for pos in [
+        0u32,
+        63,
+        64,
+        65,
+        4095,
+        u32::MAX - 64,
+        u32::MAX - 1,
+        u32::MAX,
+    ] {
+        let mut cipher = new();
+        cipher.seek(pos);
+        // `cipher` computes the position from the end of the buffered block,
+        // which does not fit into `u32` in the last partial block of the range
+        assert_eq!(cipher.current_pos::<u64>(), u64::from(pos));
+        if pos <= u32::MAX - 63 || pos % 64 == 0 {
+            assert_eq!(cipher.current_pos::<u32>(), pos);
+        }
+        let (mut a, mut b) = ([0u8; 130], [0u8; 130]);
+        cipher.apply_keystream(&mut a);
+        keystream_at(pos.into(), &mut b);
+        assert_eq!(a, b, "u32 position {pos}");
+
+        // the position now exceeds the `u32` range
+        if pos > u32::MAX - 130 {
+            assert!(cipher.try_current_pos::<u32>().is_err());
+        }
+        assert_eq!(cipher.current_pos::<u64>(), u64::from(pos) + 130);
+    }
+
+    // `u64`, `u128` and `usize` positions across the carry into the high
+    // counter word, which must not be truncated to 32 bits
+    let base = ((1u64 << 32) - 2) * 64;
+    for off in [0, 1, 63, 64, 127, 128, 129, 192, 255] {
+        let pos = base + off as u64;
+        let expected = &EXPECTED_LONG_CARRY[off..];
+
+        let mut buf = vec![0u8; expected.len()];
+        let mut cipher = new();
+        cipher.seek(pos);
+        cipher.apply_keystream(&mut buf);
+        assert_eq!(buf, expected, "u64 offset {off}");
+
+        let mut buf = vec![0u8; expected.len()];
+        let mut cipher = new();
+        cipher.seek(u128::from(pos));
+        assert_eq!(cipher.current_pos::<u128>(), u128::from(pos));
+        cipher.apply_keystream(&mut buf);
+        assert_eq!(buf, expected, "u128 offset {off}");
+
+        #[cfg(target_pointer_width = "64")]
+        {
+            let mut buf = vec![0u8; expected.len()];
+            let mut cipher = new();
+            cipher.seek(pos as usize);
+            assert_eq!(cipher.current_pos::<usize>(), pos as usize);
+            cipher.apply_keystream(&mut buf);
+            assert_eq!(buf, expected, "usize offset {off}");
+        }
+    }
+
+    // positions past the end of the keystream are rejected
+    let mut cipher = new();
+    assert!(cipher.try_seek(u128::from(u64::MAX) * 64 + 64).is_err());
+    assert_eq!(cipher.current_pos::<u64>(), 0);
+}
+
+/// Encrypting a memory-mapped file region by region at unaligned offsets
+#[cfg(unix)]
+#[test]
+fn salsa20_apply_keystream_at_mmap() {
+    use cipher::consts::U10;
+    use salsa20::SalsaCore;
+    use std::{fs, os::fd::AsRawFd};
+
+    let len = 64 * 1024 + 100;
+    let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+    let mut expected = plaintext.clone();
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+
+    let path = std::env::temp_dir().join(format!("salsa20-mmap-{}", std::process::id()));
+    fs::write(&path, &plaintext).unwrap();
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+
+    // SAFETY: the file is private to this test and stays open while mapped
+    let map = unsafe {
+        let ptr = libc::mmap(
+            core::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        );
+        assert_ne!(ptr, libc::MAP_FAILED);
+        core::slice::from_raw_parts_mut(ptr as *mut u8, len)
+    };
+
+    let core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut offset = 0;
+    for region in [1, 62, 1000, 64 * 300, 5, 64 * 700 + 37, len] {
+        let end = (offset + region).min(len);
+        core.apply_keystream_at(offset as u64, &mut map[offset..end]);
+        offset = end;
+    }
+    assert_eq!(core.current_block(), 0);
+
+    // SAFETY: `map` is not used after unmapping
+    unsafe {
+        assert_eq!(libc::msync(map.as_mut_ptr().cast(), len, libc::MS_SYNC), 0);
+        assert_eq!(libc::munmap(map.as_mut_ptr().cast(), len), 0);
+    }
+    drop(file);
+    let ciphertext = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(ciphertext == expected);
+
+    // regions at the carry into the high counter word
+    let base = ((1u64 << 32) - 2) * 64;
+    let mut buf = [0u8; 256];
+    for (start, end) in [(0, 3), (3, 64), (64, 200), (200, 256)] {
+        core.apply_keystream_at(base + start as u64, &mut buf[start..end]);
+    }
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+}
+
+/// Examples from the `quarterround`, `columnround` and `doubleround` sections
+/// of the Salsa20 specification.
+#[test]
+fn salsa20_spec_rounds() {
+    use salsa20::{double_round, quarter_round};
+
+    let qr = |y: [u32; 4]| {
+        let mut state = [0u32; 16];
+        state[..4].copy_from_slice(&y);
+        quarter_round(0, 1, 2, 3, &mut state);
+        <[u32; 4]>::try_from(&state[..4]).unwrap()
+    };
+    assert_eq!(qr([0, 0, 0, 0]), [0, 0, 0, 0]);
+    assert_eq!(
+        qr([1, 0, 0, 0]),
+        [0x08008145, 0x00000080, 0x00010200, 0x20500000]
+    );
+    assert_eq!(
+        qr([0xe7e8c006, 0xc4f9417d, 0x6479b4b2, 0x68c67137]),
+        [0xe876d72b, 0x9361dfd5, 0xf1460244, 0x948541a3],
+    );
+    assert_eq!(
+        qr([0xd3917c5b, 0x55f1c407, 0x52a58a7a, 0x8f887a3b]),
+        [0x3e2f308c, 0xd90a8f36, 0x6ab2a923, 0x2883524c],
+    );
+
+    // a single column round
+    let mut state = [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0];
+    quarter_round(0, 4, 8, 12, &mut state);
+    quarter_round(5, 9, 13, 1, &mut state);
+    quarter_round(10, 14, 2, 6, &mut state);
+    quarter_round(15, 3, 7, 11, &mut state);
+    assert_eq!(
+        state,
+        [0x10090288, 0, 0, 0, 0x00000101, 0, 0, 0, 0x00020401, 0, 0, 0, 0x40a04001, 0, 0, 0,],
+    );
+
+    let mut state = [0u32; 16];
+    state[0] = 1;
+    double_round(&mut state);
+    assert_eq!(
+        state,
+        [
+            0x8186a22d, 0x0040a284, 0x82479210, 0x06929051, 0x08000090, 0x02402200, 0x00004000,
+            0x00800000, 0x00010200, 0x20400000, 0x08008104, 0x00000000, 0x20500000, 0xa0000040,
+            0x0008180a, 0x612a8020,
+        ],
+    );
+}
+
+#[test]
+fn salsa20_try_new() {
+    use cipher::{consts::U10, InvalidLength, StreamCipherCoreWrapper};
+    use salsa20::SalsaCore;
+
+    type Core = SalsaCore<U10, U32>;
+
+    // too short
+    assert_eq!(
+        Core::try_new(&KEY_LONG[..31], &IV_LONG).err(),
+        Some(InvalidLength)
+    );
+    assert_eq!(
+        Core::try_new(&KEY_LONG, &IV_LONG[..7]).err(),
+        Some(InvalidLength)
+    );
+    assert_eq!(Core::try_new(&[], &[]).err(), Some(InvalidLength));
+
+    // too long
+    assert_eq!(Core::try_new(&[0; 33], &IV_LONG).err(), Some(InvalidLength));
+    assert_eq!(Core::try_new(&KEY_LONG, &[0; 9]).err(), Some(InvalidLength));
+
+    // a 16-byte key is only accepted by the 128-bit key variant
+    assert!(Core::try_new(&KEY_LONG[..16], &IV_LONG).is_err());
+    assert!(SalsaCore::<U10, U16>::try_new(&KEY_LONG[..16], &IV_LONG).is_ok());
+
+    // correct lengths
+    let core = Core::try_new(&KEY_LONG, &IV_LONG).unwrap();
+    let mut cipher = StreamCipherCoreWrapper::from_core(core);
+    let mut buf = [0; 64];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG[..64]);
+}
+
+#[test]
+fn salsa20_try_new_malformed() {
+    use cipher::{consts::U10, InvalidLength, KeyIvInit};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let keys: [&[u8]; 4] = [&[], &[0x42], &KEY_LONG[..15], &KEY_LONG[..31]];
+    for key in keys {
+        assert_eq!(
+            SalsaCore::<U10, U32>::try_new(key, &IV_LONG).err(),
+            Some(InvalidLength),
+            "{}-byte key",
+            key.len()
+        );
+        assert_eq!(
+            SalsaCore::<U10, U16>::try_new(key, &IV_LONG).err(),
+            Some(InvalidLength),
+            "{}-byte key",
+            key.len()
+        );
+        assert_eq!(
+            SalsaCore::<U10, U32>::builder(key, &IV_LONG).build().err(),
+            Some(InvalidLength),
+            "{}-byte key",
+            key.len()
+        );
+        assert!(Salsa20::new_from_slices(key, &IV_LONG).is_err());
+        assert!(XSalsaCore::<U10>::new_from_slices(key, &IV_XSALSA20).is_err());
+    }
+
+    let nonces: [&[u8]; 3] = [&[], &[0x24], &IV_LONG[..7]];
+    for nonce in nonces {
+        assert_eq!(
+            SalsaCore::<U10, U32>::try_new(&KEY_LONG, nonce).err(),
+            Some(InvalidLength),
+            "{}-byte nonce",
+            nonce.len()
+        );
+        assert_eq!(
+            SalsaCore::<U10, U16>::try_new(&KEY_LONG[..16], nonce).err(),
+            Some(InvalidLength),
+            "{}-byte nonce",
+            nonce.len()
+        );
+    }
+}
+
+#[test]
+fn salsa20_nonce128() {
+    use cipher::{consts::U10, StreamCipherCore};
+    use salsa20::{Salsa20Nonce128, SalsaNonce128Core};
+
+    const NONCE: [u8; 16] = hex!("03010401050902060508090703090302");
+    const EXPECTED: [u8; 64] = hex!(
+        "08A4233ABD5797EF6727C7F83F5F1362F5E12B1FD3647FD244C1C08F6B6206E9"
+        "3AB88A0EA358A713E4716CDA10C7D6D7094E8A98579C15DD225F675E86DAC18E"
+    );
+    // upper half of the nonce is `u64::MAX`
+    const NONCE_MAX: [u8; 16] = hex!("0301040105090206FFFFFFFFFFFFFFFF");
+    const EXPECTED_MAX: [u8; 64] = hex!(
+        "7B0E7DA71B4C23CFED41F9A2F19DBCE466CC00F2597C35CDB9E97F7F9924098A"
+        "7651CF4BDC8FD1B48BE7BC6EFE07B462B3828E032E8F7FC236C587584ECDAF18"
+    );
+
+    for (nonce, expected) in [(NONCE, EXPECTED), (NONCE_MAX, EXPECTED_MAX)] {
+        let mut cipher = Salsa20Nonce128::new(&KEY_LONG.into(), &nonce.into());
+        let mut buf = [0; 64];
+        cipher.apply_keystream(&mut buf);
+        assert_eq!(buf, expected);
+        assert!(cipher.try_apply_keystream(&mut [0; 1]).is_err());
+
+        // byte-wise application stops at the end of the block as well
+        let mut cipher = Salsa20Nonce128::new(&KEY_LONG.into(), &nonce.into());
+        let mut buf = [0; 64];
+        for b in buf.iter_mut() {
+            cipher.apply_keystream(core::slice::from_mut(b));
+        }
+        assert_eq!(buf, expected);
+        assert!(cipher.try_apply_keystream(&mut [0; 1]).is_err());
+
+        // a longer buffer is rejected without touching it
+        let mut cipher = Salsa20Nonce128::new(&KEY_LONG.into(), &nonce.into());
+        let mut buf = [0; 65];
+        assert!(cipher.try_apply_keystream(&mut buf).is_err());
+        assert_eq!(buf, [0; 65]);
+    }
+
+    // same as the Salsa20 block at the position given by the nonce's upper half
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &NONCE[..8].try_into().unwrap());
+    cipher.seek(u64::from_le_bytes(NONCE[8..].try_into().unwrap()) as u128 * 64);
+    let mut buf = [0; 64];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED);
+
+    let core = SalsaNonce128Core::<U10>::new(&KEY_LONG.into(), &NONCE.into());
+    assert_eq!(core.remaining_blocks(), Some(1));
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn salsa20_manually_zeroize() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper};
+    use salsa20::{ManuallyZeroize, SalsaCore};
+
+    let mut core = ManuallyZeroize::new(SalsaCore::<U10, U32>::new(
+        &KEY_LONG.into(),
+        &IV_LONG.into(),
+    ));
+    assert_eq!(core.block()[..], EXPECTED_LONG[..64]);
+
+    // the all-zero state is a fixed point of the rounds, so the keystream of
+    // a blanked state is all zeros
+    core.zeroize();
+    assert_eq!(core.block()[..], [0; 64]);
+
+    // a wrapped core works as a full cipher
+    let core = ManuallyZeroize::new(SalsaCore::<U10, U32>::new(
+        &KEY_LONG.into(),
+        &IV_LONG.into(),
+    ));
+    let mut cipher = StreamCipherCoreWrapper::from_core(core);
+    let mut buf = [0; 256];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG);
+}
+
+/// Salsa20/8 core test vector of RFC 7914, section 8, and `scryptBlockMix`
+/// with `r = 1` built on it
+#[cfg(feature = "zeroize")]
+#[test]
+fn salsa8_core_raw() {
+    use cipher::consts::U4;
+    use salsa20::{SalsaCore, SalsaCoreRaw};
+
+    fn words(block: &[u8; 64]) -> [u32; 16] {
+        let mut state = [0u32; 16];
+        for (word, chunk) in state.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        state
+    }
+
+    fn salsa8_core(block: &mut [u8; 64]) {
+        *block = SalsaCoreRaw::<U4>::from_raw_state_le(words(block))
+            .block()
+            .into();
+    }
+
+    let mut block = hex!(
+        "7e879a214f3ec9867ca940e641718f26"
+        "baee555b8c61c1b50df846116dcd3b1d"
+        "ee24f319df9b3d8514121e4b5ac5aa32"
+        "76021d2909c74829edebc68db8b8c25e"
+    );
+    salsa8_core(&mut block);
+    assert_eq!(
+        block,
+        hex!(
+            "a41f859c6608cc993b81cacb020cef05"
+            "044b2181a2fd337dfd7b1c6396682f29"
+            "b4393168e3c9e6bcfe6bc5b7a06d96ba"
+            "e424cc102c91745c24ad673dc7618f81"
+        )
+    );
+
+    let mut b = [[0u8; 64]; 2];
+    for (i, byte) in b.as_flattened_mut().iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let mut x = b[1];
+    let mut y = [[0u8; 64]; 2];
+    for (bi, yi) in b.iter().zip(y.iter_mut()) {
+        x.iter_mut().zip(bi).for_each(|(x, b)| *x ^= b);
+        salsa8_core(&mut x);
+        *yi = x;
+    }
+
+    // the same with the zeroizing core
+    let mut x = b[1];
+    for (bi, yi) in b.iter().zip(y.iter()) {
+        x.iter_mut().zip(bi).for_each(|(x, b)| *x ^= b);
+        let out = SalsaCore::<U4, U32>::from_raw_state_le(words(&x)).block();
+        assert_eq!(out[..], yi[..]);
+        x = out.into();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn salsa20_secret_key() {
+    use cipher::zeroize::Zeroize;
+    use core::mem::MaybeUninit;
+    use salsa20::{SecretKey, SecretNonce};
+
+    let key = SecretKey::new(KEY_LONG.into());
+    let nonce = SecretNonce::new(IV_LONG.into());
+    let mut cipher = Salsa20::new(&key, &nonce);
+    let mut buf = [0u8; 64];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG[..64]);
+    assert_eq!(AsRef::<[u8]>::as_ref(&key), KEY_LONG);
+    assert_eq!(format!("{key:?}"), "SecretKey { .. }");
+
+    let mut key = key.clone();
+    key.zeroize();
+    assert_eq!(key[..], [0; KEY_BYTES]);
+
+    // best-effort check of the zeroization on drop, which relies on the
+    // wrapper being `repr(transparent)`
+    let mut slot = MaybeUninit::new(SecretKey::<U32>::new(KEY_LONG.into()));
+    let bytes = slot.as_ptr().cast::<[u8; KEY_BYTES]>();
+    unsafe {
+        assert_eq!(*bytes, KEY_LONG);
+        slot.as_mut_ptr().drop_in_place();
+        assert_eq!(*bytes, [0; KEY_BYTES]);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn salsa20_explicit_zeroize() {
+    use cipher::{consts::U10, StreamCipherCore, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let before = core.block();
+    core.set_block_pos(5);
+    core.zeroize();
+    assert_eq!(core.get_block_pos(), 0);
+    let after = core.block();
+    assert_ne!(before, after);
+    assert_eq!(after[..], [0; 64]);
+
+    let mut core = XSalsaCore::<U10>::new(&KEY_LONG.into(), &IV_XSALSA20.into());
+    let mut before = Default::default();
+    core.write_keystream_block(&mut before);
+    core.zeroize();
+    assert_eq!(core.get_block_pos(), 0);
+    let mut after = Default::default();
+    core.write_keystream_block(&mut after);
+    assert_ne!(before, after);
+}
+
+#[test]
+fn salsa20_rekey() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&[0x42; 32].into(), &[0x24; 8].into());
+    core.set_block_pos(1234);
+    core.rekey(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.get_block_pos(), 0);
+
+    let mut cipher = StreamCipherCoreWrapper::from_core(core);
+    let mut buf = [0; 256];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG);
+
+    // 128-bit keys
+    let key = &KEY_LONG[..16];
+    let mut core = SalsaCore::<U10, U16>::new(&[0x42; 16].into(), &[0x24; 8].into());
+    core.rekey(key.try_into().unwrap(), &IV_LONG.into());
+    let mut expected = SalsaCore::<U10, U16>::new(key.try_into().unwrap(), &IV_LONG.into());
+    assert_eq!(core.block(), expected.block());
+}
+
+#[test]
+fn checked_key_constants() {
+    use salsa20::{checked_constants, constants};
+
+    let words = |s: &[u8; 16]| -> [u32; 4] {
+        let mut words = [0; 4];
+        for (w, c) in words.iter_mut().zip(s.chunks(4)) {
+            *w = u32::from_le_bytes(c.try_into().unwrap());
+        }
+        words
+    };
+
+    assert_eq!(constants(16), words(b"expand 16-byte k"));
+    assert_eq!(constants(32), words(b"expand 32-byte k"));
+    assert_eq!(constants(16), salsa20::TAU);
+    assert_eq!(constants(32), salsa20::SIGMA);
+    assert_eq!(checked_constants(16), Some(constants(16)));
+    assert_eq!(checked_constants(32), Some(constants(32)));
+
+    for key_len in [0, 8, 15, 17, 24, 31, 33, 64, 100] {
+        assert_eq!(checked_constants(key_len), None, "key length {key_len}");
+    }
+
+    // unsupported key sizes are rejected by the fallible constructor
+    let core =
+        salsa20::SalsaCore::<cipher::consts::U10, cipher::consts::U24>::try_new(&[0; 24], &IV_LONG);
+    assert!(core.is_err());
+}
+
+#[test]
+fn salsa20_core_eq() {
+    use cipher::{consts::U10, StreamCipherCore};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let mut a = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let b = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(a, b);
+    let _ = a.block();
+    assert_ne!(a, b);
+    assert_ne!(b, SalsaCore::new(&KEY_LONG.into(), &[0; 8].into()));
+
+    let mut a = XSalsaCore::<U10>::new(&KEY_LONG.into(), &IV_XSALSA20.into());
+    let b = XSalsaCore::<U10>::new(&KEY_LONG.into(), &IV_XSALSA20.into());
+    assert_eq!(a, b);
+    a.write_keystream_block(&mut Default::default());
+    assert_ne!(a, b);
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn salsa20_core_hash() {
+    use cipher::consts::U10;
+    use salsa20::SalsaCore;
+    use std::{collections::HashMap, hash::BuildHasher};
+
+    let hasher = std::hash::RandomState::new();
+    let mut a = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let b = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(hasher.hash_one(&a), hasher.hash_one(&b));
+
+    let mut cache = HashMap::new();
+    cache.insert(b.clone(), "block 0");
+    let _ = a.block();
+    assert_ne!(hasher.hash_one(&a), hasher.hash_one(&b));
+    cache.insert(a.clone(), "block 1");
+    assert_eq!(cache.len(), 2);
+
+    let c = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(cache[&c], "block 0");
+    assert_eq!(cache[&a], "block 1");
+}
+
+#[test]
+#[cfg(all(feature = "paranoid", debug_assertions))]
+#[should_panic(expected = "keystream reuse")]
+fn salsa20_paranoid_reuse() {
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 100];
+    cipher.apply_keystream(&mut buf);
+
+    // seeking forward and continuing is fine
+    cipher.seek(300);
+    cipher.apply_keystream(&mut buf);
+
+    // reusing the keystream from position 300 on panics
+    cipher.seek(256);
+    cipher.apply_keystream(&mut buf);
+}
+
+#[test]
+#[cfg(all(feature = "paranoid", debug_assertions))]
+fn salsa20_paranoid_consumed_positions() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::{ConsumedPositions, SalsaCore};
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.consumed_positions().blocks(), 0);
+    assert_eq!(core.consumed_positions().digest(), 0);
+
+    core.apply_keystream_blocks(&mut [0; 4 * 64]).unwrap();
+    core.set_block_pos(10);
+    core.apply_keystream_blocks(&mut [0; 2 * 64]).unwrap();
+    let consumed = core.consumed_positions();
+    assert_eq!(consumed.blocks(), 6);
+    assert_eq!(consumed.last(), 10..12);
+    assert_eq!(
+        consumed.digest(),
+        ConsumedPositions::digest_of([0, 1, 2, 3, 10, 11])
+    );
+
+    // the same positions in a different order give the same digest
+    let mut other = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    other.set_block_pos(10);
+    other.apply_keystream_blocks(&mut [0; 2 * 64]).unwrap();
+    other.set_block_pos(0);
+    other.apply_keystream_blocks(&mut [0; 4 * 64]).unwrap();
+    assert_eq!(other.consumed_positions().digest(), consumed.digest());
+
+    // block 1 is outside of the most recent range, so reusing it does not
+    // panic, but shows up in the audit
+    core.set_block_pos(1);
+    core.apply_keystream_blocks(&mut [0; 64]).unwrap();
+    let consumed = core.consumed_positions();
+    assert_eq!(consumed.blocks(), 7);
+    assert_eq!(consumed.last(), 1..2);
+    assert_ne!(
+        consumed.digest(),
+        ConsumedPositions::digest_of([0, 1, 2, 3, 10, 11, 4])
+    );
+    assert_eq!(
+        consumed.digest(),
+        ConsumedPositions::digest_of([0, 1, 1, 2, 3, 10, 11])
+    );
+
+    // re-keying starts a new audit
+    core.rekey(&KEY_LONG.into(), &IV_LONG.into());
+    assert_eq!(core.consumed_positions().blocks(), 0);
+}
+
+/// The keystream streamed into a hash equals the hash of a buffered copy
+#[test]
+fn salsa20_write_keystream_to() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    /// FNV-1a, which processes the data byte by byte like a streaming digest
+    struct Fnv(u64);
+
+    impl Fnv {
+        fn update(&mut self, data: &[u8]) {
+            for b in data {
+                self.0 = (self.0 ^ u64::from(*b)).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+    }
+
+    for n in [0, 1, 64, 100, 256, 257, 1000, 4096] {
+        let mut expected = vec![0u8; n];
+        soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+        let mut buffered = Fnv(0xcbf2_9ce4_8422_2325);
+        buffered.update(&expected);
+
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        let mut streamed = Fnv(0xcbf2_9ce4_8422_2325);
+        let mut chunks = 0;
+        core.write_keystream_to(
+            |chunk| {
+                assert!(!chunk.is_empty() && chunk.len() <= 256);
+                chunks += 1;
+                streamed.update(chunk);
+            },
+            n,
+        );
+        assert_eq!(streamed.0, buffered.0, "{n} bytes");
+        assert_eq!(chunks, n.div_ceil(256));
+        assert_eq!(core.get_block_pos(), n.div_ceil(64) as u64);
+    }
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn salsa20_write_keystream_to_digest() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+    use sha2::{Digest, Sha256};
+
+    for n in [0, 1, 64, 100, 256, 257, 1000, 4096] {
+        let mut buffered = vec![0u8; n];
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        assert_eq!(core.generate_keystream(&mut buffered), n);
+
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        let mut hasher = Sha256::new();
+        core.write_keystream_to_digest(&mut hasher, n);
+        assert_eq!(hasher.finalize(), Sha256::digest(&buffered), "{n} bytes");
+        assert_eq!(core.get_block_pos(), n.div_ceil(64) as u64);
+    }
+}
+
+#[test]
+fn salsa20_keystream_hex() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY1.into(), &IV0.into());
+    let mut hex = String::new();
+    core.write_keystream_hex(&mut hex, 64).unwrap();
+    assert_eq!(
+        hex,
+        concat!(
+            "e3be8fdd8beca2e3ea8ef9475b29a6e7",
+            "003951e1097a5c38d23b7a5fad9f6844",
+            "b22c97559e2723c7cbbd3fe4fc8d9a07",
+            "44652a83e72a9c461876af4d7ef1a117",
+        )
+    );
+
+    // a partial block discards the rest of it
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut hex = String::new();
+    core.write_keystream_hex(&mut hex, 100).unwrap();
+    let expected: String = EXPECTED_LONG[..100]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    assert_eq!(hex, expected);
+    assert_eq!(core.get_block_pos(), 2);
+}
+
+#[test]
+fn salsa20_raw_state_roundtrip() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let pos = (1 << 32) - 2;
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(pos);
+
+    // the words are in canonical order whatever the backend layout
+    let state = core.clone().into_raw_state();
+    let c = THIRTY_TWO_BYTE_KEY_CONSTANTS;
+    assert_eq!([state[0], state[5], state[10], state[15]], c);
+    assert_eq!([state[8], state[9]], [pos as u32, (pos >> 32) as u32]);
+
+    for mut restored in [
+        SalsaCore::<U10, U32>::from_raw_state(state),
+        SalsaCore::<U10, U32>::from(state),
+    ] {
+        assert_eq!(restored.get_block_pos(), pos);
+        let mut buf = [0u8; 256];
+        assert_eq!(restored.generate_keystream(&mut buf), buf.len());
+        assert_eq!(buf, EXPECTED_LONG_CARRY);
+    }
+}
+
+#[test]
+fn salsa20_core_builder() {
+    use cipher::{consts::U10, InvalidLength, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, SalsaCoreBuilder};
+
+    type Builder<'a> = SalsaCoreBuilder<'a, U10, U32>;
+
+    let key = KEY_LONG.to_vec();
+    let nonce = IV_LONG.to_vec();
+
+    // default counter
+    let mut core = Builder::new(&key, &nonce).build().unwrap();
+    assert_eq!(core.get_block_pos(), 0);
+    assert_eq!(core.block()[..], EXPECTED_LONG[..64]);
+
+    // custom counter
+    let mut core = SalsaCore::<U10, U32>::builder(&key, &nonce)
+        .counter((1 << 32) - 2)
+        .build()
+        .unwrap();
+    assert_eq!(core.get_block_pos(), (1 << 32) - 2);
+    let mut buf = [0u8; 256];
+    assert_eq!(core.generate_keystream(&mut buf), buf.len());
+    assert_eq!(buf, EXPECTED_LONG_CARRY);
+
+    // invalid lengths
+    assert_eq!(
+        Builder::new(&key[..31], &nonce).build().err(),
+        Some(InvalidLength)
+    );
+    assert_eq!(
+        Builder::new(&key, &nonce[..7]).counter(1).build().err(),
+        Some(InvalidLength)
+    );
+    assert_eq!(Builder::new(&[], &[]).build().err(), Some(InvalidLength));
+    assert!(Builder::new(&[0; 33], &nonce).build().is_err());
+    assert!(Builder::new(&key[..16], &nonce).build().is_err());
+    assert!(SalsaCoreBuilder::<U10, U16>::new(&key[..16], &nonce)
+        .build()
+        .is_ok());
+}
+
+/// Subkey derived for the nonce of the NaCl `secretbox` test, see `core2.c`
+/// in the NaCl tests
+#[test]
+fn xsalsa20_subkey() {
+    use cipher::consts::U10;
+    use salsa20::XSalsaCore;
+
+    let key = hex!("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+    let nonce = hex!("69696ee955b62b73cd62bda875fc73d6 8219e0036b7a0b37");
+    let subkey = XSalsaCore::<U10>::subkey(&key.into(), &nonce.into());
+    assert_eq!(
+        subkey,
+        hex!("dc908dda0b9344a953629b733820778880f3ceb421bb61b91cbd4c3e66256ce4")
+    );
+
+    let nonce = nonce.into();
+    let (input, inner) = XSalsaCore::<U10>::split_nonce(&nonce);
+    assert_eq!(input, &hex!("69696ee955b62b73cd62bda875fc73d6"));
+    assert_eq!(inner, &hex!("8219e0036b7a0b37"));
+
+    // the inner Salsa20 core uses the subkey and the last 8 nonce bytes
+    let mut cipher = XSalsa20::new(&key.into(), &nonce);
+    let mut expected = Salsa20::new(&subkey.into(), inner.into());
+    let (mut a, mut b) = ([0u8; 200], [0u8; 200]);
+    cipher.apply_keystream(&mut a);
+    expected.apply_keystream(&mut b);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn xsalsa20_into_inner() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::XSalsaCore;
+
+    let mut expected = [0u8; 256];
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    cipher.apply_keystream(&mut expected);
+
+    let core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    let mut inner = StreamCipherCoreWrapper::from_core(core.into_inner());
+    let mut buf = [0u8; 256];
+    inner.apply_keystream(&mut buf);
+    assert_eq!(buf, expected);
+
+    // the block position carries over
+    let mut core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    core.set_block_pos(3);
+    let mut inner = core.into_inner();
+    assert_eq!(inner.get_block_pos(), 3);
+    assert_eq!(inner.block()[..], expected[192..]);
+}
+
+/// XSalsa20 keystream of the `stream3` test of libsodium, for the key and
+/// nonce of the `secretbox` test
+#[test]
+fn xsalsa20_libsodium() {
+    use cipher::{consts::U10, KeyIvInit};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let key = hex!("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+    let nonce = hex!("69696ee955b62b73cd62bda875fc73d6 8219e0036b7a0b37");
+
+    let mut cipher = XSalsa20::new(&key.into(), &nonce.into());
+    let mut buf = [0u8; 32];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(
+        buf,
+        hex!("eea6a7251c1e72916d11c2cb214d3c252539121d8e234e652d651fa4c8cff880")
+    );
+
+    // bulk keystream goes through the same backend as Salsa20
+    let core = XSalsaCore::<U10>::new(&key.into(), &nonce.into());
+    let salsa = SalsaCore::<U10, U32>::new(&key.into(), &nonce[16..].try_into().unwrap());
+    assert_eq!(core.par_blocks(), salsa.par_blocks());
+
+    let subkey = XSalsaCore::<U10>::subkey(&key.into(), &nonce.into());
+    let mut buf = vec![0u8; 1 << 20];
+    XSalsa20::new(&key.into(), &nonce.into()).apply_keystream(&mut buf);
+    let mut expected = vec![0u8; 1 << 20];
+    soft_keystream(&subkey, &nonce[16..].try_into().unwrap(), 10, &mut expected);
+    assert!(buf == expected);
+}
+
+/// First HSalsa20 output of the NaCl `core1` test
+#[test]
+fn hsalsa20_kdf() {
+    use cipher::{consts::U10, KeyInit};
+    use salsa20::{hsalsa, HSalsa20Kdf};
+
+    let key = hex!("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+    let kdf = HSalsa20Kdf::new(&key.into());
+    assert_eq!(
+        kdf.derive(&[0; 16])[..],
+        hex!("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389")
+    );
+
+    let input = hex!("69696ee955b62b73cd62bda875fc73d6");
+    assert_eq!(
+        kdf.clone().derive(&input),
+        hsalsa::<U10>(&key.into(), &input.into())
+    );
+    assert_eq!(format!("{kdf:?}"), "HSalsaKdf { double_rounds: 10, .. }");
+}
+
+#[test]
+fn hsalsa20_array_and_bytes() {
+    use cipher::{array::Array, consts::U10};
+    use salsa20::{hsalsa, hsalsa_many, XSalsaCore};
+
+    let key = hex!("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+    let nonce = hex!("69696ee955b62b73cd62bda875fc73d6 8219e0036b7a0b37");
+
+    let array: Array<u8, U32> = hsalsa::<U10>(&key.into(), nonce[..16].try_into().unwrap());
+    let bytes: [u8; 32] = XSalsaCore::<U10>::subkey(&key.into(), &nonce.into());
+    let mut many = [[0u8; 32]];
+    hsalsa_many::<U10>(&key.into(), &[nonce[..16].try_into().unwrap()], &mut many);
+    assert_eq!(array[..], bytes);
+    assert_eq!(many[0], bytes);
+    assert_eq!(<[u8; 32]>::from(array), bytes);
+
+    // the array is accepted as key as is
+    let mut a = Salsa20::new(&array, &nonce[16..].try_into().unwrap());
+    let mut b = XSalsa20::new(&key.into(), &nonce.into());
+    let (mut x, mut y) = ([0u8; 64], [0u8; 64]);
+    a.apply_keystream(&mut x);
+    b.apply_keystream(&mut y);
+    assert_eq!(x, y);
+}
+
+/// XSalsa20 keystream for `KEY_XSALSA20` and `IV_XSALSA20` at block position
+/// 2^32 - 1, crossing into the high counter word
+const EXPECTED_XSALSA20_CARRY: [u8; 128] = hex!(
+    "116aa6f794b153b5e9923a8c47750604"
+    "d2243322345bde9fe912d71aa863f26f"
+    "911f7cec7e563211d14f3fcaf3af0421"
+    "4b502cc70c9557577623221901fb74b2"
+    "d7e8376adabe31920b5710416909328d"
+    "2f40aae5fc6e5689171389fb5b37142f"
+    "eab43a559f2d9984f433983324285070"
+    "e3ffc930a1582445c7d8b5c88a887b21"
+);
+
+#[test]
+fn xsalsa20_seek_carry() {
+    use cipher::{consts::U10, StreamCipherCore, StreamCipherSeekCore};
+    use salsa20::XSalsaCore;
+
+    let base = ((1u64 << 32) - 1) * 64;
+    for off in [0, 1, 63, 64, 100, 127] {
+        let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+        cipher.seek(base + off as u64);
+        assert_eq!(cipher.current_pos::<u64>(), base + off as u64);
+        let mut buf = [0u8; 128];
+        cipher.apply_keystream(&mut buf[off..]);
+        assert_eq!(
+            buf[off..],
+            EXPECTED_XSALSA20_CARRY[off..],
+            "mismatch at offset {off}"
+        );
+        assert_eq!(cipher.current_pos::<u64>(), base + 128);
+    }
+
+    let mut core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    core.set_block_pos((1 << 32) - 1);
+    assert_eq!(core.get_block_pos(), (1 << 32) - 1);
+    let mut block = Default::default();
+    core.write_keystream_block(&mut block);
+    assert_eq!(core.get_block_pos(), 1 << 32);
+    core.write_keystream_block(&mut block);
+    assert_eq!(block[..], EXPECTED_XSALSA20_CARRY[64..]);
+
+    // decrypting after a large seek restores the plaintext
+    let plaintext: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let pos = 0x0123_4567_89ab_cdefu128;
+    let mut buf = plaintext.clone();
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    cipher.seek(pos);
+    cipher.apply_keystream(&mut buf);
+    assert!(buf != plaintext);
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    cipher.seek(pos);
+    cipher.apply_keystream(&mut buf);
+    assert!(buf == plaintext);
+    assert_eq!(cipher.current_pos::<u128>(), pos + 1000);
+
+    // the last block position is still reachable, but no byte past it
+    let mut cipher = XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    assert!(cipher.try_seek(u128::from(u64::MAX) * 64).is_ok());
+    assert!(cipher.try_seek(1u128 << 70).is_err());
+}
+
+#[test]
+fn salsa20_apply_keystream_partial() {
+    use salsa20::ApplyKeystreamPartial;
+
+    // budget ending mid-block, then continuing from the boundary
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 256];
+    assert_eq!(cipher.apply_keystream_partial(&mut buf, 100).unwrap(), 100);
+    assert_eq!(buf[..100], EXPECTED_LONG[..100]);
+    assert_eq!(buf[100..], [0u8; 156]);
+    assert_eq!(cipher.current_pos::<u64>(), 100);
+    assert_eq!(
+        cipher
+            .apply_keystream_partial(&mut buf[100..], 1000)
+            .unwrap(),
+        156
+    );
+    assert_eq!(buf, EXPECTED_LONG);
+
+    // budget equal to the full length
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 256];
+    assert_eq!(cipher.apply_keystream_partial(&mut buf, 256).unwrap(), 256);
+    assert_eq!(buf, EXPECTED_LONG);
+    assert_eq!(cipher.current_pos::<u64>(), 256);
+
+    // empty budget
+    assert_eq!(cipher.apply_keystream_partial(&mut buf, 0).unwrap(), 0);
+    assert_eq!(cipher.current_pos::<u64>(), 256);
+
+    // exhausted keystream
+    cipher.seek(u128::from(u64::MAX) * 64 - 10);
+    assert!(cipher.apply_keystream_partial(&mut buf, 11).is_err());
+    assert_eq!(cipher.current_pos::<u128>(), u128::from(u64::MAX) * 64 - 10);
+    assert_eq!(cipher.apply_keystream_partial(&mut buf, 10).unwrap(), 10);
+}
+
+#[cfg(feature = "test-vectors")]
+#[test]
+fn salsa20_ecrypt_vectors() {
+    use cipher::{consts::U10, InvalidLength};
+    use salsa20::test_vectors::{keystream_block, ECRYPT_SALSA20};
+
+    assert!(ECRYPT_SALSA20.iter().any(|v| v.key.len() == 16));
+    assert!(ECRYPT_SALSA20.iter().any(|v| v.key.len() == 32));
+    for v in ECRYPT_SALSA20 {
+        for (block, expected) in v.blocks {
+            let actual = keystream_block::<U10>(v.key, &v.iv, *block).unwrap();
+            assert_eq!(actual, *expected, "{}, block {block}", v.name);
+        }
+    }
+
+    assert_eq!(
+        keystream_block::<U10>(&KEY1, &IV0, 0).unwrap(),
+        EXPECTED_KEY1_IV0
+    );
+    assert_eq!(
+        keystream_block::<U10>(&[0; 24], &IV0, 0),
+        Err(InvalidLength)
+    );
+    assert_eq!(
+        keystream_block::<U10>(&KEY1, &[0; 7], 0),
+        Err(InvalidLength)
+    );
+}
+
+#[cfg(feature = "test-vectors")]
+#[test]
+fn salsa20_export_keystream() {
+    use cipher::{consts::U10, InvalidLength};
+    use salsa20::{
+        test_vectors::{export_keystream, ECRYPT_SALSA20},
+        SalsaCore,
+    };
+
+    let mut out = [0u8; 10 * 64];
+    export_keystream::<U10>(&KEY_LONG, &IV_LONG, &mut out).unwrap();
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    for block in out.chunks(64) {
+        assert_eq!(block, &core.block()[..]);
+    }
+
+    let v = &ECRYPT_SALSA20[0];
+    assert_eq!(v.key.len(), 16);
+    let mut out = [0u8; 8 * 64];
+    export_keystream::<U10>(v.key, &v.iv, &mut out).unwrap();
+    for (block, expected) in v.blocks {
+        let i = *block as usize * 64;
+        assert_eq!(out[i..][..64], expected[..], "{}, block {block}", v.name);
+    }
+
+    export_keystream::<U10>(&KEY1, &IV0, &mut []).unwrap();
+    assert_eq!(
+        export_keystream::<U10>(&KEY1, &IV0, &mut [0; 65]),
+        Err(InvalidLength)
+    );
+    assert_eq!(
+        export_keystream::<U10>(&[0; 24], &IV0, &mut [0; 64]),
+        Err(InvalidLength)
+    );
+}
+
+#[test]
+fn salsa20_generate_keystream_uninit() {
+    use cipher::consts::U10;
+    use core::mem::MaybeUninit;
+    use salsa20::SalsaCore;
+
+    // not a multiple of the internal chunk size nor of the block size
+    let len = 3 * 512 + 100;
+    let mut buf: Box<[MaybeUninit<u8>]> = (0..len).map(|_| MaybeUninit::uninit()).collect();
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let ks = core.generate_keystream_uninit(&mut buf);
+    assert_eq!(ks.len(), len);
+    assert_eq!(ks[..EXPECTED_LONG.len()], EXPECTED_LONG);
+    let mut expected = vec![0u8; len];
+    soft_keystream(&KEY_LONG, &IV_LONG, 10, &mut expected);
+    assert_eq!(ks[..], expected[..]);
+    assert_eq!(core.current_block(), len.div_ceil(64) as u64);
+
+    assert!(core.generate_keystream_uninit(&mut []).is_empty());
+}
+
+#[test]
+fn salsa20_encrypt_chunks() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut expected = [0u8; 3 * 256 + 100];
+    for (i, b) in expected.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let mut buf = expected;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.encrypt_chunks::<256>(&mut buf);
+    assert_eq!(core.get_block_pos(), 14);
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+    assert_eq!(buf, expected);
+
+    // continuing across the 32-bit counter boundary with a small scratch
+    let mut buf = [0u8; 1000];
+    let mut expected = buf;
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::from(u32::MAX) - 3);
+    let mut cipher = StreamCipherCoreWrapper::from_core(core.clone());
+    core.encrypt_chunks::<64>(&mut buf[..500]);
+    core.encrypt_chunks::<64>(&mut buf[512..]);
+    cipher.apply_keystream(&mut expected[..512]);
+    cipher.apply_keystream(&mut expected[512..]);
+    assert_eq!(buf[..500], expected[..500]);
+    assert_eq!(buf[512..], expected[512..]);
+}
+
+#[test]
+#[should_panic(expected = "block counter overflow")]
+fn salsa20_encrypt_chunks_overflow() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::MAX - 1);
+    core.encrypt_chunks::<128>(&mut [0u8; 65]);
+}
+
+#[test]
+fn salsa20_apply_keystream_with_progress() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    type Core = SalsaCore<U10, U32>;
+
+    let start = u64::from(u32::MAX) - 3;
+    let step = Core::PROGRESS_BLOCKS as u64;
+    let mut buf = vec![0x5a; 2 * 64 * Core::PROGRESS_BLOCKS + 100];
+    let mut expected = buf.clone();
+
+    let mut core = Core::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(start);
+    let mut cipher = cipher::StreamCipherCoreWrapper::from_core(core.clone());
+    let mut reported = Vec::new();
+    core.apply_keystream_with_progress(&mut buf, |pos| reported.push(pos));
+    cipher.apply_keystream(&mut expected);
+    assert_eq!(buf, expected);
+
+    assert_eq!(
+        reported,
+        [start + step, start + 2 * step, start + 2 * step + 2]
+    );
+    assert_eq!(reported.last(), Some(&core.get_block_pos()));
+
+    let mut reported = Vec::new();
+    core.apply_keystream_with_progress(&mut [], |pos| reported.push(pos));
+    assert!(reported.is_empty());
+}
+
+#[test]
+fn salsa20_session() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::{Salsa20Session, SalsaCore, SalsaSession};
+
+    let sizes = [1, 2, 3, 13, 64, 0, 100, 255, 256, 257, 7, 1000, 63, 1];
+    let mut session = Salsa20Session::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+    for (i, &size) in sizes.iter().enumerate() {
+        let mut msg: Vec<u8> = (0..size).map(|j| (i + j) as u8).collect();
+        let mut expected = msg.clone();
+        session.encrypt(&mut msg);
+        cipher.apply_keystream(&mut expected);
+        assert_eq!(msg, expected, "message {i}");
+    }
+
+    // only the remaining blocks are generated at the end of the keystream
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::MAX - 2);
+    let mut cipher = StreamCipherCoreWrapper::from_core(core.clone());
+    let mut session = SalsaSession::from_core(core);
+    let (mut a, mut b) = ([0u8; 128], [0u8; 128]);
+    session.encrypt(&mut a[..50]);
+    session.encrypt(&mut a[50..]);
+    cipher.apply_keystream(&mut b);
+    assert_eq!(a, b);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn salsa20_par_apply_keystream() {
+    use cipher::{consts::U10, StreamCipherCoreWrapper, StreamCipherSeekCore};
+    use salsa20::{SalsaCore, XSalsaCore};
+
+    let mut expected = vec![0u8; 64 << 20];
+    for (i, b) in expected.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let mut buf = expected.clone();
+
+    let core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.par_apply_keystream(&mut buf);
+    assert_eq!(core.get_block_pos(), 0);
+    Salsa20::new(&KEY_LONG.into(), &IV_LONG.into()).apply_keystream(&mut expected);
+    assert!(buf == expected);
+
+    // unaligned length, starting in the middle of the keystream
+    let mut buf = vec![0u8; (1 << 20) + 37];
+    let mut expected = buf.clone();
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::from(u32::MAX) - 100);
+    core.par_apply_keystream(&mut buf);
+    StreamCipherCoreWrapper::from_core(core).apply_keystream(&mut expected);
+    assert!(buf == expected);
+
+    let mut buf = vec![0u8; (1 << 20) + 37];
+    let mut expected = buf.clone();
+    let core = XSalsaCore::<U10>::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into());
+    core.par_apply_keystream(&mut buf);
+    XSalsa20::new(&KEY_XSALSA20.into(), &IV_XSALSA20.into()).apply_keystream(&mut expected);
+    assert!(buf == expected);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+#[should_panic(expected = "block counter overflow")]
+fn salsa20_par_apply_keystream_overflow() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    core.set_block_pos(u64::MAX - 1);
+    core.par_apply_keystream(&mut [0u8; 65]);
+}
+
+#[test]
+fn salsa16_generic_rounds() {
+    use cipher::consts::U8;
+    use salsa20::{Salsa, SalsaCore};
+
+    assert_eq!(SalsaCore::<U8, U32>::ROUNDS, 16);
+
+    let mut cipher = Salsa::<U8>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 64];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, EXPECTED_LONG_SALSA16);
+
+    let mut buf = vec![0u8; 1000];
+    let mut expected = buf.clone();
+    let mut cipher = Salsa::<U8>::new(&KEY_LONG.into(), &IV_LONG.into());
+    cipher.apply_keystream(&mut buf);
+    soft_keystream(&KEY_LONG, &IV_LONG, 8, &mut expected);
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn salsa20_apply_keystream_blocks() {
+    use cipher::{consts::U10, StreamCipherSeekCore};
+    use salsa20::SalsaCore;
+
+    // Cover lengths which are not a multiple of the widest parallel batch
+    for blocks in [0, 1, 3, 4, 5, 8, 9, 17, 37] {
+        let len = blocks * 64;
+        let mut buf: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let mut expected = buf.clone();
+
+        let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+        assert!(core.par_blocks() >= 1);
+        core.apply_keystream_blocks(&mut buf).unwrap();
+        assert_eq!(core.get_block_pos(), blocks as u64);
+
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &IV_LONG.into());
+        cipher.apply_keystream(&mut expected);
+        assert!(buf == expected, "mismatch at {blocks} blocks");
+    }
+
+    let mut core = SalsaCore::<U10, U32>::new(&KEY_LONG.into(), &IV_LONG.into());
+    let mut buf = [0u8; 65];
+    assert!(core.apply_keystream_blocks(&mut buf).is_err());
+    assert_eq!(buf, [0u8; 65]);
+    assert_eq!(core.get_block_pos(), 0);
+}
+
+#[cfg(feature = "rand_core")]
+#[test]
+fn salsa20_rng_reproducible() {
+    use salsa20::{
+        rand_core::{RngCore, SeedableRng},
+        Salsa20Rng,
+    };
+
+    let mut rng1 = Salsa20Rng::from_seed(KEY_LONG.into());
+    let mut rng2 = Salsa20Rng::from_seed(KEY_LONG.into());
+    let mut buf1 = [0u8; 300];
+    let mut buf2 = [0u8; 300];
+    rng1.fill_bytes(&mut buf1);
+    rng2.fill_bytes(&mut buf2);
+    assert_eq!(buf1, buf2);
+    assert_eq!(rng1.next_u64(), rng2.next_u64());
+
+    // the output is the keystream for an all-zero nonce
+    let mut expected = [0u8; 300];
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &[0; IV_BYTES].into());
+    cipher.apply_keystream(&mut expected);
+    assert_eq!(buf1, expected);
+}
+
+#[cfg(feature = "rand_core")]
+#[test]
+fn salsa20_rng_reseed() {
+    use salsa20::{
+        rand_core::{CryptoRng, SeedableRng},
+        Salsa20Rng,
+    };
+
+    fn fill<R: CryptoRng>(rng: &mut R) -> [u8; 100] {
+        let mut buf = [0u8; 100];
+        rng.fill_bytes(&mut buf);
+        buf
+    }
+
+    let mut rng1 = Salsa20Rng::from_seed(KEY_LONG.into());
+    let mut rng2 = Salsa20Rng::from_seed(KEY_LONG.into());
+    assert_eq!(fill(&mut rng1), fill(&mut rng2));
+    assert_eq!(fill(&mut rng1), fill(&mut rng2));
+
+    // reseeding one of them makes the outputs diverge
+    rng2.reseed(KEY1.into());
+    assert_eq!(rng2.get_word_pos(), 0);
+    assert_ne!(fill(&mut rng1), fill(&mut rng2));
+
+    // and reseeding restarts the output like a new generator
+    rng1.reseed(KEY1.into());
+    let mut rng3 = Salsa20Rng::from_seed(KEY1.into());
+    let buf = fill(&mut rng3);
+    assert_eq!(fill(&mut rng1), buf);
+    assert_ne!(fill(&mut rng2), buf);
+}
+
+#[cfg(feature = "rand_core")]
+#[test]
+fn salsa20_rng_word_pos() {
+    use salsa20::{
+        rand_core::{RngCore, SeedableRng},
+        Salsa20Rng,
+    };
+
+    let mut keystream = [0u8; 1024];
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &[0; IV_BYTES].into());
+    cipher.apply_keystream(&mut keystream);
+
+    let mut rng = Salsa20Rng::from_seed(KEY_LONG.into());
+    assert_eq!(rng.get_word_pos(), 0);
+
+    // words pulled one at a time keep the position in sync with the cipher
+    for w in 0..40 {
+        let x = rng.next_u32();
+        assert_eq!(x.to_le_bytes(), keystream[4 * w..][..4]);
+        assert_eq!(rng.get_word_pos(), w as u128 + 1);
+    }
+
+    for w in [0u128, 1, 15, 16, 17, 100, 250] {
+        rng.set_word_pos(w);
+        assert_eq!(rng.get_word_pos(), w);
+
+        let mut buf = [0u8; 13];
+        rng.fill_bytes(&mut buf);
+        let start = 4 * w as usize;
+        assert_eq!(buf, keystream[start..][..13]);
+
+        // a partial word is consumed entirely
+        assert_eq!(rng.get_word_pos(), w + 4);
+
+        let mut cipher = Salsa20::new(&KEY_LONG.into(), &[0; IV_BYTES].into());
+        cipher.seek(start);
+        let mut expected = [0u8; 200];
+        cipher.apply_keystream(&mut expected);
+        rng.set_word_pos(w);
+        let mut buf = [0u8; 200];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(buf, expected);
+    }
+
+    // positions past the 32-bit block counter
+    let w = (1u128 << 36) + 3;
+    rng.set_word_pos(w);
+    assert_eq!(rng.get_word_pos(), w);
+    let mut cipher = Salsa20::new(&KEY_LONG.into(), &[0; IV_BYTES].into());
+    cipher.seek(4 * w as u64);
+    let mut expected = [0u8; 8];
+    cipher.apply_keystream(&mut expected);
+    assert_eq!(rng.next_u64().to_le_bytes(), expected);
+}
+
 #[test]
 #[ignore]
 fn salsa20_offsets() {
@@ -0,0 +1,157 @@
+//! Seek regression tests around the carry into the high counter word.
+//!
+//! The x86 SIMD backends keep the high counter word in `state[5]` instead of
+//! `state[9]`, so every seek crossing the 2^32 block boundary is checked
+//! against a reference keystream. The reference blocks are built from the
+//! canonical raw state, independent of `set_block_pos`. CI runs this with
+//! both layouts, see the `salsa20_force_soft` configuration.
+
+#![allow(unexpected_cfgs)]
+
+use cipher::{
+    consts::{U10, U32},
+    KeyIvInit, StreamCipher, StreamCipherSeek,
+};
+use salsa20::{Salsa20, SalsaCore};
+
+const KEY: [u8; 32] = *b"seek regression key for salsa20!";
+
+const NONCE: [u8; 8] = *b"boundary";
+
+/// Byte position of the first block with a non-zero high counter word.
+const CARRY: u64 = 64 << 32;
+
+/// Seek positions relative to [`CARRY`] and lengths of the processed data.
+const CORPUS: &[(i64, usize)] = &[
+    (-257, 600),
+    (-256, 256),
+    (-256, 257),
+    (-255, 320),
+    (-193, 1),
+    (-192, 192),
+    (-129, 130),
+    (-128, 128),
+    (-127, 255),
+    (-65, 1),
+    (-65, 2),
+    (-65, 66),
+    (-64, 64),
+    (-64, 65),
+    (-63, 63),
+    (-63, 64),
+    (-33, 100),
+    (-2, 4),
+    (-1, 1),
+    (-1, 2),
+    (-1, 64),
+    (-1, 65),
+    (-1, 129),
+    (0, 1),
+    (0, 63),
+    (0, 64),
+    (0, 65),
+    (0, 512),
+    (1, 63),
+    (1, 64),
+    (1, 127),
+    (31, 33),
+    (63, 1),
+    (63, 2),
+    (64, 64),
+    (65, 200),
+    (127, 1),
+    (200, 1000),
+];
+
+/// Reference keystream starting at byte `pos`, built block by block from the
+/// canonical raw state.
+fn reference(pos: u64, len: usize) -> Vec<u8> {
+    let init = SalsaCore::<U10, U32>::new(&KEY.into(), &NONCE.into()).state_words();
+    let first = pos / 64;
+    let skip = (pos % 64) as usize;
+    let blocks = (skip + len).div_ceil(64) as u64;
+
+    let mut keystream = Vec::new();
+    for block in first..first + blocks {
+        let mut state = init;
+        state[8] = block as u32;
+        state[9] = (block >> 32) as u32;
+        let mut core = SalsaCore::<U10, U32>::from_raw_state(state);
+        keystream.extend_from_slice(&core.block());
+    }
+    keystream.drain(..skip);
+    keystream.truncate(len);
+    keystream
+}
+
+fn position(rel: i64) -> u64 {
+    CARRY.checked_add_signed(rel).unwrap()
+}
+
+fn plaintext(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7) as u8).collect()
+}
+
+#[test]
+fn seek_corpus_roundtrip() {
+    for &(rel, len) in CORPUS {
+        let pos = position(rel);
+        let plaintext = plaintext(len);
+        let expected: Vec<u8> = plaintext
+            .iter()
+            .zip(reference(pos, len))
+            .map(|(p, k)| p ^ k)
+            .collect();
+
+        let mut buf = plaintext.clone();
+        let mut cipher = Salsa20::new(&KEY.into(), &NONCE.into());
+        cipher.seek(pos);
+        assert_eq!(cipher.current_pos::<u64>(), pos, "seek to {rel:+}");
+        cipher.apply_keystream(&mut buf);
+        assert!(buf == expected, "encrypt at {rel:+}, length {len}");
+        assert_eq!(
+            cipher.current_pos::<u64>(),
+            pos + len as u64,
+            "position after {rel:+}, length {len}"
+        );
+
+        let mut cipher = Salsa20::new(&KEY.into(), &NONCE.into());
+        cipher.seek(pos);
+        cipher.apply_keystream(&mut buf);
+        assert!(buf == plaintext, "decrypt at {rel:+}, length {len}");
+    }
+}
+
+/// Data encrypted in two calls, seeking to the start of the second part in
+/// between, which may lie within a block.
+#[test]
+fn seek_corpus_split() {
+    for &(rel, len) in CORPUS {
+        let pos = position(rel);
+        let mid = len / 2;
+        let mut buf = vec![0u8; len];
+
+        let mut cipher = Salsa20::new(&KEY.into(), &NONCE.into());
+        cipher.seek(pos);
+        cipher.apply_keystream(&mut buf[..mid]);
+        cipher.seek(pos + mid as u64);
+        cipher.apply_keystream(&mut buf[mid..]);
+        assert!(buf == reference(pos, len), "split at {rel:+}, length {len}");
+    }
+}
+
+#[test]
+fn seek_corpus_core_block_pos() {
+    use cipher::StreamCipherSeekCore;
+
+    for &(rel, _) in CORPUS {
+        let block = position(rel) / 64;
+        let mut core = SalsaCore::<U10, U32>::new(&KEY.into(), &NONCE.into());
+        core.set_block_pos(block);
+        assert_eq!(core.get_block_pos(), block, "block of {rel:+}");
+
+        let words = core.state_words();
+        assert_eq!(words[8], block as u32, "low word of {rel:+}");
+        assert_eq!(words[9], (block >> 32) as u32, "high word of {rel:+}");
+    }
+}